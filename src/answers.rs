@@ -0,0 +1,242 @@
+//! Expected answers for an input directory, used by `--check` and the per-day answer tests.
+//!
+//! The answers file sits next to the inputs it belongs to and has one `day part answer` line per part. Multi-line
+//! answers escape newlines as `\n` (and backslashes as `\\`). Trailing whitespace on each line of an answer, and blank
+//! lines around it, are ignored when comparing.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const ANSWERS_FILE: &str = "answers.txt";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    Wrong { expected: String },
+    /// The answers file has no entry for the part.
+    Unchecked,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    answers: BTreeMap<(usize, usize), String>,
+}
+
+/// Trims the trailing whitespace of every line and any blank lines before or after the answer.
+fn normalise(answer: &str) -> String {
+    let lines: Vec<&str> = answer.lines().map(str::trim_end).collect();
+    let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let last = lines.iter().rposition(|line| !line.is_empty()).map_or(first, |last| last + 1);
+    lines[first..last].join("\n")
+}
+
+fn escape(answer: &str) -> String {
+    answer.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(answer: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(answer.len());
+    let mut chars = answer.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => return Err(format!("unknown escape \\{}", other)),
+            None => return Err("answer ends with a lone backslash".to_string()),
+        }
+    }
+    Ok(unescaped)
+}
+
+impl Answers {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Answers, Box<dyn std::error::Error>> {
+        Ok(std::fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn get(&self, day: usize, part: usize) -> Option<&str> {
+        self.answers.get(&(day, part)).map(String::as_str)
+    }
+
+    pub fn check(&self, day: usize, part: usize, answer: &str) -> Verdict {
+        match self.get(day, part) {
+            Some(expected) if normalise(expected) == normalise(answer) => Verdict::Correct,
+            Some(expected) => Verdict::Wrong { expected: escape(&normalise(expected)) },
+            None => Verdict::Unchecked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnswersParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AnswersParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid answers file on line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AnswersParseError {}
+
+impl FromStr for Answers {
+    type Err = AnswersParseError;
+
+    fn from_str(s: &str) -> Result<Answers, AnswersParseError> {
+        let mut answers = Answers::default();
+        for (line_idx, line) in s.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| AnswersParseError { line: line_idx + 1, message };
+            let mut fields = line.splitn(3, ' ');
+            let mut parse_num = |what: &str, max: usize| match fields.next().map(|num_str| num_str.parse::<usize>()) {
+                Some(Ok(num)) if (1..=max).contains(&num) => Ok(num),
+                _ => Err(error(format!("expected a {} from 1 to {}", what, max))),
+            };
+            let (day, part) = (parse_num("day", 25)?, parse_num("part", 2)?);
+            let answer = unescape(fields.next().unwrap_or("")).map_err(error)?;
+            if answers.answers.insert((day, part), answer).is_some() {
+                return Err(error(format!("day {} part {} is listed twice", day, part)));
+            }
+        }
+        Ok(answers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner;
+    use crate::solution;
+    use std::io::Write;
+
+    #[test]
+    fn answers_file_format() {
+        let answers: Answers = "# comment\n1 1 42\n8 2 \\n**  \\n* *\\n\\n\n".parse().unwrap();
+        assert_eq!(answers.get(8, 2), Some("\n**  \n* *\n\n"));
+        assert_eq!(answers.check(8, 2, "** \n* *\n"), Verdict::Correct);
+        assert_eq!(answers.check(1, 1, "43"), Verdict::Wrong { expected: "42".to_string() });
+        assert_eq!(answers.check(1, 2, "43"), Verdict::Unchecked);
+
+        assert_eq!("1 1 2\n1 1 3".parse::<Answers>().unwrap_err().message, "day 1 part 1 is listed twice");
+        assert_eq!("26 1 2".parse::<Answers>().unwrap_err().message, "expected a day from 1 to 25");
+        assert_eq!("1 3 2".parse::<Answers>().unwrap_err().line, 1);
+        assert_eq!("1 1 a\\b".parse::<Answers>().unwrap_err().message, "unknown escape \\b");
+    }
+
+    fn check_answer(day: usize, part: usize) {
+        let input_dir = Path::new(runner::DEFAULT_INPUT_DIR);
+        let answers = Answers::load(input_dir.join(ANSWERS_FILE)).unwrap();
+        let input = runner::load_input(input_dir, day).unwrap();
+        let answer = (solution::find(day).unwrap().solve)(part, input).expect("part isn't implemented");
+        match answers.check(day, part, &answer) {
+            Verdict::Correct => (),
+            verdict => panic!("Day {} part {} answered {:?}: {:?}", day, part, answer, verdict),
+        }
+    }
+
+    /// Checks that a part still gives a wrong or unverifiable answer, or panics, running it through the runner so a panic
+    /// is caught. The report goes straight to stderr, since the test harness hides `eprintln!` output from passing tests.
+    fn check_known_wrong(day: usize, part: usize, reason: &str) {
+        let input_dir = Path::new(runner::DEFAULT_INPUT_DIR);
+        let answers = Answers::load(input_dir.join(ANSWERS_FILE)).unwrap();
+        let outcome = runner::run_days(day..=day, input_dir, Some(&answers)).into_iter().find(|outcome| outcome.part == part).unwrap();
+        let problem = match (&outcome.status, &outcome.verdict) {
+            (runner::Status::Panicked(message), _) => format!("panicked: {}", message),
+            (runner::Status::Ok, Some(Verdict::Wrong { expected })) => {
+                format!("answered {:?}, expected {:?}", outcome.answer.unwrap_or_default(), expected)
+            },
+            (runner::Status::Ok, Some(Verdict::Unchecked)) => {
+                format!("answered {:?}, with no known answer to check it against", outcome.answer.unwrap_or_default())
+            },
+            (runner::Status::Ok, Some(Verdict::Correct)) => {
+                panic!("Day {} part {} now gives the right answer; move it to `answer_tests!`", day, part)
+            },
+            (status, verdict) => panic!("Day {} part {}: {:?}, {:?}", day, part, status, verdict),
+        };
+        let report = format!("Day {} part {} is known to be wrong ({}): {}\n", day, part, reason, problem);
+        std::io::stderr().write_all(report.as_bytes()).unwrap();
+    }
+
+    /// Generates one test per part, checking the answer for the input in `inputs/2019` against the answers file.
+    macro_rules! answer_tests {
+        ($($(#[$attr:meta])* $name:ident: $day:expr, $part:expr;)*) => {
+            $(
+                #[test]
+                $(#[$attr])*
+                fn $name() {
+                    check_answer($day, $part);
+                }
+            )*
+        };
+    }
+
+    answer_tests! {
+        day1_part1: 1, 1;
+        day1_part2: 1, 2;
+        day2_part1: 2, 1;
+        day2_part2: 2, 2;
+        day3_part1: 3, 1;
+        day3_part2: 3, 2;
+        day4_part1: 4, 1;
+        day4_part2: 4, 2;
+        day5_part1: 5, 1;
+        day5_part2: 5, 2;
+        day6_part1: 6, 1;
+        day6_part2: 6, 2;
+        day7_part1: 7, 1;
+        day7_part2: 7, 2;
+        day8_part1: 8, 1;
+        day8_part2: 8, 2;
+        day9_part1: 9, 1;
+        day9_part2: 9, 2;
+        day10_part1: 10, 1;
+        day11_part1: 11, 1;
+        day11_part2: 11, 2;
+        day12_part1: 12, 1;
+        #[ignore = "brute-force simulation doesn't finish on the real input"]
+        day12_part2: 12, 2;
+        day13_part1: 13, 1;
+        day13_part2: 13, 2;
+        day19_part1: 19, 1;
+        day19_part2: 19, 2;
+        day22_part1: 22, 1;
+    }
+
+    /// Generates one test per part that's known to give the wrong answer or panic, checking that it still does and
+    /// reporting it on stderr. Each test with an expected answer fails once its part is fixed, as a reminder to move it
+    /// to `answer_tests!`.
+    macro_rules! known_wrong_tests {
+        ($($name:ident: $day:expr, $part:expr, $reason:expr;)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    check_known_wrong($day, $part, $reason);
+                }
+            )*
+        };
+    }
+
+    known_wrong_tests! {
+        day10_part2_known_wrong: 10, 2, "the vaporisation order is incorrect";
+        day14_part1_known_wrong: 14, 1, "total_ore never counts ORE and ignores reaction batch sizes";
+        day16_part1_known_wrong: 16, 1, "day16_gen keeps the trailing newline, and part 1 runs the FFT on only 8 digits and returns \"\"";
+        day21_part1_known_wrong: 21, 1, "the springscript falls into a hole";
+    }
+
+    #[test]
+    fn unwritten_parts_not_implemented() {
+        for &day in &[14, 16, 21, 22] {
+            let input = runner::load_input(Path::new(runner::DEFAULT_INPUT_DIR), day).unwrap();
+            assert_eq!((solution::find(day).unwrap().solve)(2, input), None, "day {} part 2", day);
+        }
+    }
+}
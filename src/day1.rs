@@ -1,4 +1,4 @@
-fn part1(input: String) -> i32 {
+pub(crate) fn part1(input: String) -> i32 {
     input
         .lines()
         .flat_map(|s| s.parse::<i32>())
@@ -6,7 +6,7 @@ fn part1(input: String) -> i32 {
         .sum()
 }
 
-fn part2(input: String) -> i32 {
+pub(crate) fn part2(input: String) -> i32 {
     input
         .lines()
         .flat_map(|s| s.parse::<i32>())
@@ -22,12 +22,4 @@ fn part2(input: String) -> i32 {
         .sum()
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new(2019, 1);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day1: 1, String = std::convert::identity, i32, i32);
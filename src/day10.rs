@@ -150,12 +150,4 @@ pub fn part2(input: Vec<(f32, f32)>) -> i32 { // currently incorrect
     -6969
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 10, day10_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day10: 10, Vec<(f32, f32)> = day10_gen, usize, i32);
@@ -1,4 +1,6 @@
 use crate::intcode::prelude::*;
+use crate::intcode::device::{Color, FrameError, Framer, RobotEvent, Turn};
+use crate::intcode::loader::intcode_gen;
 use std::collections::HashMap;
 
 mod dir {
@@ -8,32 +10,10 @@ mod dir {
     pub const LEFT: i8 = 3;
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Tile {
-    Black,
-    White,
-}
-
-impl From<i64> for Tile {
-    fn from(val: i64) -> Tile {
-        match val {
-            0 => Tile::Black,
-            1 => Tile::White,
-            _ => panic!("Invalid tile value {}", val),
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone)]
-enum OutputMode {
-    Paint,
-    Turn,
-}
-
 /// Runs the hull painting robot and returns a tuple containing the number of tiles painted at least once, and a printable String with the output image, respectively.
-fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
+fn paint_hull(input: &[i64], starting_tile: Color) -> (usize, String) {
     let mut prog = Program::from(input);
-    let mut tiles: HashMap<(i32, i32), Tile> = HashMap::new();
+    let mut tiles: HashMap<(i32, i32), Color> = HashMap::new();
     tiles.insert((0, 0), starting_tile);
     let mut min_width = 1;
     let mut max_width = 1;
@@ -42,7 +22,8 @@ fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
     let mut robot_x = 0;
     let mut robot_y = 0;
     let mut robot_dir = dir::UP;
-    let mut output_mode = OutputMode::Paint;
+    let mut framer = Framer::new();
+    let mut frame_error: Option<FrameError> = None;
     let mut output_image = String::new();
     
     while !prog.is_halted() {
@@ -52,8 +33,8 @@ fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
                     IOReturn::Input(
                         if tiles.contains_key(&(robot_x, robot_y)) {
                             match tiles[&(robot_x, robot_y)] {
-                                Tile::Black => 0,
-                                Tile::White => 1,
+                                Color::Black => 0,
+                                Color::White => 1,
                             }
                         }
                         else {
@@ -62,20 +43,14 @@ fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
                     )
                 },
                 IOOperation::Output(value) => {
-                    match output_mode {
-                        OutputMode::Paint => {
-                            tiles.entry((robot_x, robot_y))
-                                .and_modify(|tile| *tile = Tile::from(value))
-                                .or_insert(Tile::from(value));
-                            output_mode = OutputMode::Turn;
+                    match framer.push(value) {
+                        Some(Ok(RobotEvent::Paint(color))) => {
+                            tiles.insert((robot_x, robot_y), color);
                         },
-                        OutputMode::Turn => {
-                            match value {
-                                // Turn left
-                                0 => robot_dir = (robot_dir - 1).rem_euclid(4),
-                                // Turn right
-                                1 => robot_dir = (robot_dir + 1).rem_euclid(4),
-                                _ => panic!("Invalid direction output {}", value),
+                        Some(Ok(RobotEvent::Turn(turn))) => {
+                            match turn {
+                                Turn::Left => robot_dir = (robot_dir - 1).rem_euclid(4),
+                                Turn::Right => robot_dir = (robot_dir + 1).rem_euclid(4),
                             }
 
                             match robot_dir {
@@ -90,14 +65,20 @@ fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
                             max_width = robot_x.max(max_width);
                             min_height = robot_y.min(min_height);
                             max_height = robot_y.max(max_height);
-
-                            output_mode = OutputMode::Paint;
-                        }
+                        },
+                        None => (),
+                        Some(Err(err)) => {
+                            frame_error = Some(err);
+                            return IOReturn::Output(ExecuteAction::Break);
+                        },
                     }
                     IOReturn::Output(ExecuteAction::Continue)
                 },
             }
         });
+        if let Some(err) = frame_error.take() {
+            panic!("{}", err);
+        }
     }
 
     output_image.push('\n');
@@ -109,8 +90,8 @@ fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
             }
             output_image.push(
                 match tiles[&(x, y)] {
-                    Tile::Black => ' ',
-                    Tile::White => '\u{2588}',
+                    Color::Black => ' ',
+                    Color::White => '\u{2588}',
                 }
             );
         }
@@ -120,29 +101,14 @@ fn paint_hull(input: &[i64], starting_tile: Tile) -> (usize, String) {
     (tiles.len(), output_image)
 }
 
-fn day11_gen(input: String) -> Vec<i64> {
-    input
-        .split(',')
-        .flat_map(|num_str| num_str.trim().parse::<i64>())
-        .collect()
-}
-
-fn part1(input: Vec<i64>) -> usize {
-    let (tiles_painted, _) = paint_hull(&input, Tile::Black);
+pub(crate) fn part1(input: Vec<i64>) -> usize {
+    let (tiles_painted, _) = paint_hull(&input, Color::Black);
     tiles_painted
 }
 
-fn part2(input: Vec<i64>) -> String {
-    let (_, output_image) = paint_hull(&input, Tile::White);
+pub(crate) fn part2(input: Vec<i64>) -> String {
+    let (_, output_image) = paint_hull(&input, Color::White);
     output_image
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 11, day11_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day11: 11, Vec<i64> = intcode_gen, usize, String);
@@ -42,7 +42,7 @@ impl std::ops::SubAssign for Vec3 {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-struct Moon {
+pub(crate) struct Moon {
     pos: Vec3,
     vel: Vec3,
 }
@@ -61,7 +61,7 @@ impl Moon {
     }
 }
 
-fn day12_gen(input: String) -> Vec<Moon> {
+pub(crate) fn day12_gen(input: String) -> Vec<Moon> {
     input
         .trim()
         .lines()
@@ -115,7 +115,7 @@ fn simulation_step(moons: &mut [Moon]) {
     }
 }
 
-fn part1(moons: Vec<Moon>) -> i64 {
+pub(crate) fn part1(moons: Vec<Moon>) -> i64 {
     let mut moons = moons;
     for _ in 0..1000 {
         simulation_step(&mut moons);
@@ -126,7 +126,7 @@ fn part1(moons: Vec<Moon>) -> i64 {
         .sum()
 }
 
-fn part2(moons: Vec<Moon>) -> u64 {
+pub(crate) fn part2(moons: Vec<Moon>) -> u64 {
     let mut moons = moons;
     let initial_state = [moons[0], moons[1], moons[2], moons[3]];
     let mut num_steps = 0_u64;
@@ -145,12 +145,4 @@ fn part2(moons: Vec<Moon>) -> u64 {
     num_steps
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 12, day12_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day12: 12, Vec<Moon> = day12_gen, i64, u64);
@@ -1,52 +1,40 @@
 use crate::intcode::prelude::*;
+use crate::intcode::device::{ArcadeEvent, ArcadeTile, FrameError, Framer};
+use crate::intcode::loader::intcode_gen;
 use std::collections::HashMap;
-use std::hint::unreachable_unchecked;
 
-fn day13_gen(input: String) -> Vec<i64> {
-    input
-        .split(',')
-        .flat_map(|num_str| num_str.trim().parse::<i64>())
-        .collect()
-}
-
-fn part1(input: Vec<i64>) -> usize {
+pub(crate) fn part1(input: Vec<i64>) -> usize {
     let mut prog = Program::from(input.as_slice());
-    let mut game_screen: HashMap<(i64, i64), i64> = HashMap::new(); // (x, y) -> tile
-    let mut output_select = 0_u8; // 0 = x, 1 = y, 2 = tile
-    let mut x = 0_i64;
-    let mut y = 0_i64;
-    let mut tile = 0_i64;
+    let mut game_screen: HashMap<(i64, i64), ArcadeTile> = HashMap::new();
+    let mut framer = Framer::new();
+    let mut frame_error: Option<FrameError> = None;
     prog.run(|io_op| {
         match io_op {
             IOOperation::Input => IOReturn::Input(0),
             IOOperation::Output(value) => {
-                match output_select {
-                    0 => x = value,
-                    1 => y = value,
-                    2 => {
-                        tile = value;
-                        game_screen.entry((x, y))
-                            .and_modify(|t| *t = tile)
-                            .or_insert(tile);
+                match framer.push(value) {
+                    Some(Ok(ArcadeEvent::Tile { x, y, tile })) => { game_screen.insert((x, y), tile); },
+                    Some(Ok(ArcadeEvent::Score(_))) | None => (),
+                    Some(Err(err)) => {
+                        frame_error = Some(err);
+                        return IOReturn::Output(ExecuteAction::Break);
                     },
-                    _ => unsafe { unreachable_unchecked() },
                 }
-                output_select = (output_select + 1) % 3;
                 IOReturn::Output(ExecuteAction::Continue)
             }
         }
     });
-    game_screen.iter().filter(|(_, &t)| t == 2).count()
+    if let Some(err) = frame_error.or_else(|| framer.finish().err()) {
+        panic!("{}", err);
+    }
+    game_screen.values().filter(|&&tile| tile == ArcadeTile::Block).count()
 }
 
-fn part2(input: Vec<i64>) -> i64 {
+pub(crate) fn part2(input: Vec<i64>) -> i64 {
     let mut prog = Program::from(input.as_slice());
-    prog.prog_mut()[0] = 2; // free play
-    let mut game_screen: HashMap<(i64, i64), i64> = HashMap::new(); // (x, y) -> tile
-    let mut output_select = 0_u8; // 0 = x, 1 = y, 2 = tile/score
-    let mut x = 0_i64;
-    let mut y = 0_i64;
-    let mut tile = 0_i64;
+    prog.memory_mut().set(0, 2); // free play
+    let mut framer = Framer::new();
+    let mut frame_error: Option<FrameError> = None;
     let mut score = 0_i64;
     let mut ball_x = 0;
     let mut paddle_x = 0;
@@ -66,42 +54,24 @@ fn part2(input: Vec<i64>) -> i64 {
                 IOReturn::Input(joystick)
             },
             IOOperation::Output(value) => {
-                match output_select {
-                    0 => x = value,
-                    1 => y = value,
-                    2 => {
-                        if x == -1 && y == 0 {
-                            score = value;
-                        }
-                        else {
-                            tile = value;
-                            if tile == 4 {
-                                ball_x = x;
-                            }
-                            else if tile == 3 {
-                                paddle_x = x;
-                            }
-                            game_screen.entry((x, y))
-                                .and_modify(|t| *t = tile)
-                                .or_insert(tile);
-                        }
+                match framer.push(value) {
+                    Some(Ok(ArcadeEvent::Score(new_score))) => score = new_score,
+                    Some(Ok(ArcadeEvent::Tile { x, tile: ArcadeTile::Ball, .. })) => ball_x = x,
+                    Some(Ok(ArcadeEvent::Tile { x, tile: ArcadeTile::Paddle, .. })) => paddle_x = x,
+                    Some(Ok(ArcadeEvent::Tile { .. })) | None => (),
+                    Some(Err(err)) => {
+                        frame_error = Some(err);
+                        return IOReturn::Output(ExecuteAction::Break);
                     },
-                    _ => unsafe { unreachable_unchecked() },
                 }
-                output_select = (output_select + 1) % 3;
                 IOReturn::Output(ExecuteAction::Continue)
             }
         }
     });
+    if let Some(err) = frame_error.or_else(|| framer.finish().err()) {
+        panic!("{}", err);
+    }
     score
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 13, day13_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day13: 13, Vec<i64> = intcode_gen, usize, i64);
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 
 type ChemMap = HashMap<String, (u32, Vec<(u32, String)>)>;
 
-fn day14_gen(input: String) -> ChemMap {
+pub(crate) fn day14_gen(input: String) -> ChemMap {
     input
         .trim()
         .lines()
@@ -50,20 +50,8 @@ fn total_ore(map: &ChemMap, chem: &str, amt_needed: u32) -> u32 {
     ore_total
 }
 
-fn part1(input: ChemMap) -> u32 {
+pub(crate) fn part1(input: ChemMap) -> u32 {
     total_ore(&input, "FUEL", 1)
 }
 
-fn part2(_input: ChemMap) -> u32 {
-    6969
-}
-
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 14, day14_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day14: 14, ChemMap = day14_gen, u32);
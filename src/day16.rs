@@ -26,14 +26,14 @@ fn fft(digits: &[i8]) -> Vec<i8> {
         .collect()
 }
 
-fn day16_gen(input: String) -> Vec<i8> {
+pub(crate) fn day16_gen(input: String) -> Vec<i8> {
     input
         .bytes()
         .map(|b| (b - b'0') as i8)
         .collect()
 }
 
-fn part1(input: Vec<i8>) -> String {
+pub(crate) fn part1(input: Vec<i8>) -> String {
     let mut work_digits = input[0..8].to_vec();
     for _ in 0..100 {
         work_digits = fft(&work_digits);
@@ -41,16 +41,4 @@ fn part1(input: Vec<i8>) -> String {
     "".to_string()
 }
 
-fn part2(_input: Vec<i8>) -> String {
-    "".to_string()
-}
-
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 16, day16_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day16: 16, Vec<i8> = day16_gen, String);
@@ -1,38 +1,15 @@
 use crate::intcode::prelude::*;
+use crate::intcode::loader::intcode_gen;
+use crate::intcode::search;
 
-fn day19_gen(input: String) -> Vec<i64> {
-    input
-        .split(',')
-        .flat_map(|num_str| num_str.trim().parse::<i64>())
-        .collect()
+pub(crate) fn part1(input: Vec<i64>) -> usize {
+    let prog = Program::from(input.as_slice());
+    let coords: Vec<(i64, i64)> = (0..50).flat_map(|y| (0..50).map(move |x| (x, y))).collect();
+    search::find_all(&prog, &coords, |&(x, y), _| vec![x, y], |_, _, output| output == [1]).len()
 }
 
-fn part1(input: Vec<i64>) -> i64 {
-    let mut prog = Program::from(input.as_slice());
-    let mut total = 0;
-    for y in 0..50 {
-        for x in 0..50 {
-            let mut input_iter = std::iter::once(x).chain(std::iter::once(y));
-            prog.reset();
-            prog.run(|io_op| {
-                match io_op {
-                    IOOperation::Input => IOReturn::Input(input_iter.next().unwrap()),
-                    IOOperation::Output(value) => {
-                        match value {
-                            1 => total += 1,
-                            _ => (),
-                        }
-                        IOReturn::Output(ExecuteAction::Continue)
-                    },
-                }
-            });
-        }
-    }
-    total
-}
-
-fn in_tractor_beam(input: &[i64], x: i64, y: i64) -> bool {
-    let mut prog = Program::from(input);
+pub(crate) fn in_tractor_beam(base_prog: &Program, x: i64, y: i64) -> bool {
+    let mut prog = base_prog.fork();
     let mut input_iter = std::iter::once(x).chain(std::iter::once(y));
     let mut output = false;
     prog.run(|io_op| {
@@ -51,18 +28,19 @@ fn in_tractor_beam(input: &[i64], x: i64, y: i64) -> bool {
     output
 }
 
-fn part2(input: Vec<i64>) -> i64 {
+pub(crate) fn part2(input: Vec<i64>) -> i64 {
+    let base_prog = Program::from(input.as_slice());
     let mut x_left = 0;
     let mut found_left = false;
     for y in 10.. { // start at 10 because input has first few missing
         'x_loop: for x in x_left.. {
-            if in_tractor_beam(&input, x, y) {
+            if in_tractor_beam(&base_prog, x, y) {
                 if !found_left {
                     x_left = x;
                     found_left = true;
                 }
 
-                if in_tractor_beam(&input, x + 99, y) && in_tractor_beam(&input, x, y + 99) {
+                if in_tractor_beam(&base_prog, x + 99, y) && in_tractor_beam(&base_prog, x, y + 99) {
                     return x * 10000 + y;
                 }
             }
@@ -75,12 +53,4 @@ fn part2(input: Vec<i64>) -> i64 {
     -6969
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 19, day19_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day19: 19, Vec<i64> = intcode_gen, usize, i64);
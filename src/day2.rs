@@ -1,61 +1,37 @@
-fn day2_gen(input: String) -> Vec<usize> {
-    input
-        .split(',')
-        .flat_map(|num| num.parse::<usize>())
-        .collect()
-}
-
-fn run_program(program: &mut [usize], input1: usize, input2: usize) -> usize {
-    program[1] = input1;
-    program[2] = input2;
-
-    for i in (0..program.len()).step_by(4) {
-        match program[i] {
-            1 => {
-                let i1 = program[i + 1];
-                let i2 = program[i + 2];
-                let i3 = program[i + 3];
-                program[i3] = program[i1] + program[i2];
-            },
-            2 => {
-                let i1 = program[i + 1];
-                let i2 = program[i + 2];
-                let i3 = program[i + 3];
-                program[i3] = program[i1] * program[i2];
-            },
-            99 => break,
-            _ => panic!("Invalid opcode at position {}!", i),
-        }
-    }
+use crate::intcode::prelude::*;
+use crate::intcode::isa::Isa;
+use crate::intcode::loader::intcode_gen;
+use crate::intcode::search;
 
-    program[0]
+/// Runs the gravity assist program with the given noun and verb under the original day 2 instruction set.
+fn run_program(prog: &mut Program, noun: i64, verb: i64) -> i64 {
+    prog.reset();
+    prog.memory_mut().set(1, noun);
+    prog.memory_mut().set(2, verb);
+    prog.run_with_inputs(&[]);
+    prog.memory()[0]
 }
 
-fn part1(input: Vec<usize>) -> usize {
-    let mut input = input;
-    run_program(&mut input, 12, 2)
+pub(crate) fn part1(input: Vec<i64>) -> i64 {
+    let mut prog = Program::from(input.as_slice());
+    prog.set_isa(Isa::Day2);
+    run_program(&mut prog, 12, 2)
 }
 
-fn part2(input: Vec<usize>) -> usize {
-    let mut program = vec![0; input.len()];
-    for verb in 0..=99 {
-        for noun in 0..=99 {
-            program.copy_from_slice(&input);
-            let num = run_program(&mut program, noun, verb);
-            if num != 19690720 { continue; }
-            return 100 * noun + verb;
-        }
+pub(crate) fn part2(input: Vec<i64>) -> i64 {
+    let mut prog = Program::from(input.as_slice());
+    prog.set_isa(Isa::Day2);
+    let pairs: Vec<(i64, i64)> = (0..=99).flat_map(|verb| (0..=99).map(move |noun| (noun, verb))).collect();
+    let setup = |&(noun, verb): &(i64, i64), prog: &mut Program| {
+        prog.memory_mut().set(1, noun);
+        prog.memory_mut().set(2, verb);
+        Vec::new()
+    };
+    let predicate = |_: &(i64, i64), prog: &Program, _: &[i64]| prog.memory()[0] == 19690720;
+    match search::find_first(&prog, &pairs, setup, predicate) {
+        Some(pair_idx) => 100 * pairs[pair_idx].0 + pairs[pair_idx].1,
+        None => panic!("No pair was found."),
     }
-
-    panic!("No pair was found.")
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 2, day2_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day2: 2, Vec<i64> = intcode_gen, i64, i64);
@@ -1,11 +1,11 @@
 #[derive(Debug, Copy, Clone)]
-enum Instruction {
+pub(crate) enum Instruction {
     DealIntoNewStack,
     DealWithIncrement(i32),
     Cut(i32),
 }
 
-fn gen(input: String) -> Vec<Instruction> {
+pub(crate) fn gen(input: String) -> Vec<Instruction> {
     input
         .lines()
         .map(|line| {
@@ -49,7 +49,7 @@ fn deal_with_increment(deck: &mut [i32], inc: i32) {
     deck.copy_from_slice(&new_deck);
 }
 
-fn part1(input: Vec<Instruction>) -> usize {
+pub(crate) fn part1(input: Vec<Instruction>) -> usize {
     let mut deck: Vec<i32> = (0..10007).collect();
     for inst in &input {
         match inst {
@@ -62,12 +62,4 @@ fn part1(input: Vec<Instruction>) -> usize {
     result
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 22, gen);
-    let part1 = Puzzle::new(1, part1);
-    //let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    //day.run(&part2)?;
-    Ok(())
-}
+solution!(Day22: 22, Vec<Instruction> = gen, usize);
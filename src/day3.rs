@@ -11,13 +11,13 @@ enum WireInstruction {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-enum GridCell {
+pub(crate) enum GridCell {
     Origin,
     OccupiedBy { wire_idx: usize, at_step: u32 },
     Intersection([u32; 2]),
 }
 
-fn day3_gen(input: String) -> WireGridMap {
+pub(crate) fn day3_gen(input: String) -> WireGridMap {
     let wires: Vec<Vec<WireInstruction>> = input
         .lines()
         .map(|line| {
@@ -100,7 +100,7 @@ fn process_cell(wire_grid: &mut WireGridMap, x: i32, y: i32, current_wire_idx: u
         .or_insert(GridCell::OccupiedBy { wire_idx: current_wire_idx, at_step: wire_steps[current_wire_idx] });
 }
 
-fn part1(input: WireGridMap) -> i32 {
+pub(crate) fn part1(input: WireGridMap) -> i32 {
     let mut closest_distance = i32::max_value();
     for ((x, y), cell) in &input {
         if let GridCell::Intersection(_) = cell {
@@ -113,7 +113,7 @@ fn part1(input: WireGridMap) -> i32 {
     closest_distance
 }
 
-fn part2(input: WireGridMap) -> i32 {
+pub(crate) fn part2(input: WireGridMap) -> i32 {
     let mut lowest_step_sum = i32::max_value();
     for ((_, _), cell) in &input {
         if let GridCell::Intersection(steps) = cell {
@@ -126,12 +126,4 @@ fn part2(input: WireGridMap) -> i32 {
     lowest_step_sum
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 3, day3_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day3: 3, WireGridMap = day3_gen, i32, i32);
@@ -17,14 +17,14 @@ fn digits(num: u32) -> [u32; 6] {
     ]
 }
 
-fn day4_gen(input: String) -> (u32, u32) {
+pub(crate) fn day4_gen(input: String) -> (u32, u32) {
     (
         input[0..6].parse::<u32>().unwrap(),
         input[7..13].parse::<u32>().unwrap()
     )
 }
 
-fn part1((lower, upper): (u32, u32)) -> usize {
+pub(crate) fn part1((lower, upper): (u32, u32)) -> usize {
     (lower..=upper)
         .filter(|&num| {
             let mut double_digit = false;
@@ -44,7 +44,7 @@ fn part1((lower, upper): (u32, u32)) -> usize {
         .count()
 }
 
-fn part2((lower, upper): (u32, u32)) -> usize {
+pub(crate) fn part2((lower, upper): (u32, u32)) -> usize {
     (lower..=upper)
         .filter(|&num| {
             let mut double_digit = false;
@@ -76,12 +76,4 @@ fn part2((lower, upper): (u32, u32)) -> usize {
         .count()
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 4, day4_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day4: 4, (u32, u32) = day4_gen, usize, usize);
@@ -1,11 +1,5 @@
 use crate::intcode::prelude::*;
-
-fn day5_gen(input: String) -> Vec<i64> {
-    input
-        .split(',')
-        .flat_map(|num_str| num_str.parse::<i64>())
-        .collect()
-}
+use crate::intcode::loader::intcode_gen;
 
 pub(crate) fn part1(input: Vec<i64>) -> i64 {
     let mut output = -6969;
@@ -39,12 +33,4 @@ pub(crate) fn part2(input: Vec<i64>) -> i64 {
     output
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 5, day5_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day5: 5, Vec<i64> = intcode_gen, i64, i64);
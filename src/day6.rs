@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-fn day6_gen(input: String) -> HashMap<String, String> {
+pub(crate) fn day6_gen(input: String) -> HashMap<String, String> {
     input
         .lines()
         .map(|line| {
@@ -11,7 +11,7 @@ fn day6_gen(input: String) -> HashMap<String, String> {
         .collect()
 }
 
-fn part1(input: HashMap<String, String>) -> i32 {
+pub(crate) fn part1(input: HashMap<String, String>) -> i32 {
     let direct_orbits = input.len() as i32;
     let mut total_indirect_orbits = 0;
     for (_, v) in &input {
@@ -28,7 +28,7 @@ fn part1(input: HashMap<String, String>) -> i32 {
     direct_orbits + total_indirect_orbits
 }
 
-fn part2(input: HashMap<String, String>) -> usize {
+pub(crate) fn part2(input: HashMap<String, String>) -> usize {
     let mut you_to_com = Vec::new();
     {
         let mut cursor = &input["YOU"];
@@ -64,12 +64,4 @@ fn part2(input: HashMap<String, String>) -> usize {
     orbital_transfers
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 6, day6_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day6: 6, HashMap<String, String> = day6_gen, i32, usize);
@@ -1,20 +1,14 @@
 use crate::intcode::prelude::*;
+use crate::intcode::loader::intcode_gen;
 use itertools::Itertools;
 
-fn day7_gen(input: String) -> Vec<i64> {
-    input
-        .split(',')
-        .flat_map(|num_str| num_str.trim().parse::<i64>())
-        .collect()
-}
-
 #[derive(Debug, Copy, Clone, Default)]
-struct AmpResult {
-    output: i64,
-    halted: bool,
+pub(crate) struct AmpResult {
+    pub(crate) output: i64,
+    pub(crate) halted: bool,
 }
 
-fn run_amplifier(prog: &mut Program, phase_setting: impl Into<Option<i64>>, input_signal: i64) -> AmpResult {
+pub(crate) fn run_amplifier(prog: &mut Program, phase_setting: impl Into<Option<i64>>, input_signal: i64) -> AmpResult {
     let mut output = 0;
     let phase_setting = phase_setting.into();
     let mut input_iter = std::iter::once(phase_setting.unwrap_or(0)).chain(std::iter::repeat(input_signal));
@@ -87,12 +81,4 @@ pub(crate) fn part2(input: Vec<i64>) -> i64 {
     highest_output
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 7, day7_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day7: 7, Vec<i64> = intcode_gen, i64, i64);
@@ -1,7 +1,7 @@
 const WIDTH: usize = 25;
 const HEIGHT: usize = 6;
 
-fn day8_gen(input: String) -> Vec<u8> {
+pub(crate) fn day8_gen(input: String) -> Vec<u8> {
     input.trim().bytes().map(|digit_ascii| digit_ascii - 0x30).collect()
 }
 
@@ -18,7 +18,7 @@ fn count_digits(input: &[u8], digit: u8) -> usize {
     input.iter().filter(|&&b| b == digit).count()
 }
 
-fn part1(input: Vec<u8>) -> usize {
+pub(crate) fn part1(input: Vec<u8>) -> usize {
     let mut fewest_zero_digits = usize::max_value();
     let mut fewest_zero_digits_idx = 0;
     for i in 0..layer_count(&input) {
@@ -36,7 +36,7 @@ fn part1(input: Vec<u8>) -> usize {
     num_one_digits * num_two_digits
 }
 
-fn part2(input: Vec<u8>) -> String {
+pub(crate) fn part2(input: Vec<u8>) -> String {
     let mut output = String::with_capacity(WIDTH * HEIGHT + HEIGHT + 1); // for newlines on each line and at the beginning
     output.push('\n'); // makes it look nicer in cargo aoc output
     for y in 0..HEIGHT {
@@ -56,12 +56,4 @@ fn part2(input: Vec<u8>) -> String {
     output
 }
 
-use aoc_helper::{AocDay, Puzzle};
-pub fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let mut day = AocDay::new_with_serializer(2019, 8, day8_gen);
-    let part1 = Puzzle::new(1, part1);
-    let part2 = Puzzle::new(2, part2);
-    day.run(&part1)?;
-    day.run(&part2)?;
-    Ok(())
-}
+solution!(Day8: 8, Vec<u8> = day8_gen, usize, String);
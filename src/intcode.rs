@@ -1,8 +1,40 @@
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+mod expect;
+pub mod compiler;
+pub mod coverage;
+pub mod crashdump;
+pub mod device;
+pub mod disasm;
+pub mod isa;
+pub mod journal;
+pub mod loader;
+pub mod memdiff;
+pub mod memory;
+pub mod optimise;
+pub mod progdiff;
+pub mod protect;
+pub mod remote;
+pub mod search;
+pub mod selfmod;
+pub mod session;
+pub mod tui;
+
 pub mod prelude {
     pub use super::{Program, IOOperation, IOReturn, ExecuteAction};
 }
 
-#[derive(Debug, Copy, Clone)]
+use coverage::Coverage;
+use crashdump::CrashRecorder;
+use isa::Isa;
+use journal::Journal;
+use memory::Memory;
+use session::SessionEvent;
+use protect::{Fault, Protection};
+use selfmod::CodeTracker;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Op {
     Add,
     Multiply,
@@ -16,7 +48,7 @@ enum Op {
     Halt,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum ParamMode {
     Position,
     Immediate,
@@ -60,6 +92,7 @@ pub enum IOReturn {
 }
 
 impl IOReturn {
+    #[allow(dead_code)]
     pub fn input_value(self) -> i64 {
         match self {
             IOReturn::Input(value) => value,
@@ -81,32 +114,16 @@ pub enum ExecuteAction {
     Break,
 }
 
-#[derive(Debug, Clone)]
-pub struct Program {
-    default_prog: Box<[i64]>,
-    prog: Vec<i64>,
-    pc: usize,
-    relative_base: usize,
-    halted: bool,
-}
-
-impl Program {
-    #[allow(dead_code)]
-    pub fn prog(&self) -> &[i64] {
-        &self.prog
-    }
-
-    #[allow(dead_code)]
-    pub fn prog_mut(&mut self) -> &mut [i64] {
-        &mut self.prog
-    }
-
-    pub fn is_halted(&self) -> bool {
-        self.halted
-    }
-
-    fn decode(&self) -> Instruction {
-        let mut instr = unsafe { *self.prog.get_unchecked(self.pc) } as u16; //gives slightly better perf on div/mod than i64
+impl Instruction {
+    /// Decodes the instruction starting at `addr`. This is the only decoder: the interpreter, the disassembler and the
+    /// optimiser all use it. Returns `None` for an unknown opcode, an unknown parameter mode or an immediate-mode write
+    /// target, which the interpreter reports as an illegal instruction before any of its effects happen.
+    pub fn decode_at(memory: &Memory, addr: usize) -> Option<Instruction> {
+        let word = memory.get(addr);
+        if !(0..=u16::MAX as i64).contains(&word) {
+            return None;
+        }
+        let mut instr = word as u16; //gives slightly better perf on div/mod than i64
         let op = instr % 100;
         instr /= 100;
         let mode0 = instr % 10;
@@ -126,69 +143,134 @@ impl Program {
             8 => (4, Op::Equals),
             9 => (2, Op::RelativeBase),
             99 => (1, Op::Halt),
-            _ => panic!("Illegal instruction {} at PC={}", self.prog[self.pc], self.pc),
+            _ => return None,
         };
+        if mode0 > 2 || mode1 > 2 || mode2 > 2 {
+            return None;
+        }
 
         let params = [
             Param {
-                value: unsafe { *self.prog.get_unchecked(self.pc + 1) },
+                value: memory.get(addr + 1),
                 mode: ParamMode::from(mode0 as u8),
             },
             Param {
-                value: unsafe { *self.prog.get_unchecked(self.pc + 2) },
+                value: memory.get(addr + 2),
                 mode: ParamMode::from(mode1 as u8),
             },
             Param {
-                value: unsafe { *self.prog.get_unchecked(self.pc + 3) },
+                value: memory.get(addr + 3),
                 mode: ParamMode::from(mode2 as u8),
             },
         ];
-        
-        Instruction { opcode, params, length }
+
+        let ins = Instruction { opcode, params, length };
+        match ins.write_param() {
+            Some(param) if param.mode == ParamMode::Immediate => None,
+            _ => Some(ins),
+        }
     }
-    
-    fn read_value(&mut self, param: Param) -> i64 {
-        let read_idx = match param.mode {
-            ParamMode::Position => param.value as usize,
-            ParamMode::Immediate => 0,
-            ParamMode::Relative => self.relative_base.wrapping_add(param.value as usize),
-        };
 
-        if let ParamMode::Position | ParamMode::Relative = param.mode {
-            if read_idx >= self.prog.len() {
-                let extend_len = read_idx as usize - (self.prog.len() - 1);
-                self.prog.extend(std::iter::repeat(0).take(extend_len + 3)); // 3-padding to guarantee being able to read 3 values for opcode decoding
-            }
+    /// The parameter this instruction writes to, if it writes to memory.
+    fn write_param(&self) -> Option<Param> {
+        match self.opcode {
+            Op::Add | Op::Multiply | Op::LessThan | Op::Equals => Some(self.params[2]),
+            Op::Input => Some(self.params[0]),
+            _ => None,
         }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    image: Memory,
+    memory: Memory,
+    pc: usize,
+    relative_base: usize,
+    halted: bool,
+    journal: Option<Journal>,
+    code_tracker: Option<CodeTracker>,
+    coverage: Option<Coverage>,
+    protection: Option<Protection>,
+    fault: Option<Fault>,
+    isa: Isa,
+    crash_recorder: Option<CrashRecorder>,
+    catch_faults: bool,
+}
+
+impl Program {
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    #[allow(dead_code)]
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    #[allow(dead_code)]
+    pub fn relative_base(&self) -> usize {
+        self.relative_base
+    }
 
+    /// Decodes the instruction at the current PC. See `Instruction::decode_at` for what doesn't decode.
+    fn decode(&self) -> Option<Instruction> {
+        Instruction::decode_at(&self.memory, self.pc)
+    }
+
+    /// The address a parameter refers to, or `None` for an immediate.
+    fn param_addr(&self, param: Param) -> Option<usize> {
         match param.mode {
-            // We can use `get_unchecked` here because by casting to usize, we know we're not negative.
-            // We've also verified that the program is large enough to contain the index.
-            ParamMode::Position | ParamMode::Relative => unsafe { *self.prog.get_unchecked(read_idx) },
-            ParamMode::Immediate => param.value,
+            ParamMode::Position => Some(param.value as usize),
+            ParamMode::Immediate => None,
+            ParamMode::Relative => Some(self.relative_base.wrapping_add(param.value as usize)),
         }
     }
 
-    fn write_value(&mut self, param: Param, write_value: i64) {
-        let write_idx = match param.mode {
-            ParamMode::Position => param.value as usize,
-            ParamMode::Immediate => panic!("Attempted to write to immediate value. PC={}, param={:?}", self.pc, param),
-            ParamMode::Relative => self.relative_base.wrapping_add(param.value as usize),
+    /// Reads a parameter without recording the access, for checks made before an instruction runs.
+    fn peek_value(&self, param: Param) -> i64 {
+        self.param_addr(param).map_or(param.value, |addr| self.memory.get(addr))
+    }
+
+    fn read_value(&mut self, param: Param) -> i64 {
+        // Reads past the end of memory see zero without growing it; only writes grow memory.
+        let read_idx = match self.param_addr(param) {
+            Some(addr) => addr,
+            None => return param.value,
         };
-        if write_idx >= self.prog.len() {
-            let extend_len = write_idx - (self.prog.len() - 1);
-            self.prog.extend(std::iter::repeat(0).take(extend_len + 3)); // 3-padding to guarantee being able to read 3 values for opcode decoding
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark_read(read_idx);
+        }
+        self.memory.get(read_idx)
+    }
+
+    fn write_value(&mut self, param: Param, write_value: i64) {
+        let write_idx = self.param_addr(param).expect("immediate-mode writes don't decode");
+        if let Some(journal) = &mut self.journal {
+            journal.record_write(write_idx, self.memory.get(write_idx));
+        }
+        if let Some(code_tracker) = &mut self.code_tracker {
+            code_tracker.check_write(self.pc, write_idx, self.memory.get(write_idx), write_value);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark_written(write_idx);
         }
 
-        // We can use `get_unchecked_mut` here because by casting to usize, we know we're not negative.
-        // We've also verified that the program is large enough to contain the index.
-        unsafe { *self.prog.get_unchecked_mut(write_idx) = write_value; }
+        self.memory.set(write_idx, write_value);
     }
 
     /// Executes the given decoded instruction, and returns whether the execution loop should pause early.
     fn execute<F>(&mut self, ins: Instruction, io_handler: &mut F) -> ExecuteAction
     where
-        F: FnMut(IOOperation) -> IOReturn
+        F: FnMut(IOOperation, &Program) -> IOReturn
     {
         let mut exec_action = ExecuteAction::Continue;
         let mut pc_increase = true;
@@ -205,15 +287,37 @@ impl Program {
                 self.write_value(ins.params[2], left_operand * right_operand);
             },
             Op::Input => {
-                self.write_value(ins.params[0], io_handler(IOOperation::Input).input_value());
+                // Answering an input request with an execution action instead of a value pauses (or retries)
+                // the input instruction without consuming anything.
+                match io_handler(IOOperation::Input, self) {
+                    IOReturn::Input(value) => {
+                        if let Some(crash_recorder) = &mut self.crash_recorder {
+                            crash_recorder.record_io(SessionEvent::Input(value));
+                        }
+                        self.write_value(ins.params[0], value);
+                    },
+                    IOReturn::Output(action) => {
+                        exec_action = action;
+                        pc_increase = false;
+                        if let Some(journal) = &mut self.journal {
+                            journal.discard_last();
+                        }
+                    },
+                }
             },
             Op::Output => {
                 let value = self.read_value(ins.params[0]);
-                exec_action = io_handler(IOOperation::Output(value)).exec_action();
+                if let Some(crash_recorder) = &mut self.crash_recorder {
+                    crash_recorder.record_io(SessionEvent::Output(value));
+                }
+                exec_action = io_handler(IOOperation::Output(value), self).exec_action();
             },
             Op::JumpIfTrue => {
                 let value = self.read_value(ins.params[0]);
                 let dest = self.read_value(ins.params[1]);
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.mark_branch(self.pc, value != 0);
+                }
                 if value != 0 {
                     self.pc = dest as usize;
                     pc_increase = false;
@@ -222,6 +326,9 @@ impl Program {
             Op::JumpIfFalse => {
                 let value = self.read_value(ins.params[0]);
                 let dest = self.read_value(ins.params[1]);
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.mark_branch(self.pc, value == 0);
+                }
                 if value == 0 {
                     self.pc = dest as usize;
                     pc_increase = false;
@@ -254,44 +361,197 @@ impl Program {
         exec_action
     }
 
-    /// Runs the current Intcode program using the provided I/O handler.
+    /// Decodes and executes the instruction at the current PC, journaling and tracking it first if those are enabled.
+    /// A fault halts the program with the PC left on the faulting instruction, before any of its effects happen.
+    fn step_with<F>(&mut self, io_handler: &mut F) -> ExecuteAction
+    where
+        F: FnMut(IOOperation, &Program) -> IOReturn
+    {
+        let pc = self.pc;
+        if let Some(crash_recorder) = &mut self.crash_recorder {
+            crash_recorder.record_step(pc);
+        }
+        if self.isa != Isa::Full && self.isa.check(self.memory.get(pc)).is_err() {
+            self.fault = Some(Fault::Unsupported { pc, instruction: self.memory.get(pc), isa: self.isa });
+            return self.stop_on_fault(pc);
+        }
+        let instruction = match self.decode() {
+            Some(instruction) => instruction,
+            None => {
+                self.fault = Some(Fault::IllegalInstruction { pc, instruction: self.memory.get(pc) });
+                return self.stop_on_fault(pc);
+            },
+        };
+        let write_addr = instruction.write_param().and_then(|param| self.param_addr(param));
+        if let Some(addr) = write_addr.filter(|&addr| addr >= memory::ADDRESS_LIMIT) {
+            self.fault = Some(Fault::AddressOutOfRange { pc, addr });
+            return self.stop_on_fault(pc);
+        }
+        if let Some(fault) = self.check_protection(instruction) {
+            self.fault = Some(fault);
+            return self.stop_on_fault(pc);
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.begin(self.pc, self.relative_base, self.memory.len());
+        }
+        if let Some(code_tracker) = &mut self.code_tracker {
+            code_tracker.mark_executed(self.pc, instruction.length);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark_executed(self.pc);
+        }
+        self.execute(instruction, io_handler)
+    }
+
+    fn stop_on_fault(&mut self, pc: usize) -> ExecuteAction {
+        self.pc = pc;
+        self.halted = true;
+        if let Some(mut crash_recorder) = self.crash_recorder.take() {
+            crash_recorder.write_dump(self);
+            self.crash_recorder = Some(crash_recorder);
+        }
+        ExecuteAction::Break
+    }
+
+    /// Runs the current Intcode program using the provided I/O handler. Panics if the program faults, unless
+    /// `catch_faults` was called.
     pub fn run<F>(&mut self, io_handler: F)
     where
         F: FnMut(IOOperation) -> IOReturn
+    {
+        let mut io_handler = io_handler;
+        self.run_with_machine(|io_op, _| io_handler(io_op));
+    }
+
+    /// Like `run`, but the I/O handler also gets a read-only view of the program. While the handler runs, `pc()` is the
+    /// address of the instruction performing the I/O.
+    pub fn run_with_machine<F>(&mut self, io_handler: F)
+    where
+        F: FnMut(IOOperation, &Program) -> IOReturn
+    {
+        self.run_until_stopped(io_handler);
+        if let Some(fault) = self.fault {
+            if !self.catch_faults {
+                panic!("Intcode fault: {}", fault);
+            }
+        }
+    }
+
+    /// Runs until the program halts, faults or the I/O handler breaks, leaving any fault for the caller to report.
+    fn run_until_stopped<F>(&mut self, io_handler: F)
+    where
+        F: FnMut(IOOperation, &Program) -> IOReturn
     {
         let mut io_handler = io_handler;
         while !self.halted {
-            let instruction = self.decode();
-            let exec_action = self.execute(instruction, &mut io_handler);
+            let exec_action = self.step_with(&mut io_handler);
             if let ExecuteAction::Break = exec_action {
                 break;
             }
         }
     }
+
+    /// Runs the program, feeding it `inputs` in order, until it halts or requests more input than was given.
+    /// Returns every value the program output. Panics on a fault like `run`.
+    pub fn run_with_inputs(&mut self, inputs: &[i64]) -> Vec<i64> {
+        let mut input_iter = inputs.iter().copied();
+        let mut outputs = Vec::new();
+        self.run(|io_op| {
+            match io_op {
+                IOOperation::Input => match input_iter.next() {
+                    Some(value) => IOReturn::Input(value),
+                    None => IOReturn::Output(ExecuteAction::Break),
+                },
+                IOOperation::Output(value) => {
+                    outputs.push(value);
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+            }
+        });
+        outputs
+    }
+
+    /// Executes a single instruction using the provided I/O handler. Does nothing if the program has halted. Unlike
+    /// `run`, a fault doesn't panic: it halts the program and is left in `fault()`.
+    #[allow(dead_code)]
+    pub fn step<F>(&mut self, io_handler: F) -> ExecuteAction
+    where
+        F: FnMut(IOOperation) -> IOReturn
+    {
+        let mut io_handler = io_handler;
+        self.step_with_machine(|io_op, _| io_handler(io_op))
+    }
+
+    /// Like `step`, but the I/O handler also gets a read-only view of the program.
+    #[allow(dead_code)]
+    pub fn step_with_machine<F>(&mut self, io_handler: F) -> ExecuteAction
+    where
+        F: FnMut(IOOperation, &Program) -> IOReturn
+    {
+        let mut io_handler = io_handler;
+        if self.halted {
+            return ExecuteAction::Break;
+        }
+        self.step_with(&mut io_handler)
+    }
  
     /// Resets the current Intcode program to its initial state.
     pub fn reset(&mut self) {
-        self.prog.clear();
-        self.prog.extend_from_slice(&self.default_prog);
-        self.prog.extend(std::iter::repeat(0).take(3)); // Add tiny padding on the end to guarantee that opcode decoding can read all values
+        self.memory.clone_from(&self.image);
         self.pc = 0;
         self.relative_base = 0;
         self.halted = false;
+        self.fault = None;
+        if let Some(journal) = &mut self.journal {
+            journal.clear();
+        }
+        if let Some(code_tracker) = &mut self.code_tracker {
+            code_tracker.clear();
+        }
+        if let Some(crash_recorder) = &mut self.crash_recorder {
+            crash_recorder.clear();
+        }
+    }
+
+    /// Snapshots the running machine. The fork shares memory pages with this program until either one writes to them,
+    /// so forking only costs a page table copy. If this program is journaled, the fork is too, but starts with an empty
+    /// journal.
+    pub fn fork(&self) -> Program {
+        Program {
+            image: self.image.clone(),
+            memory: self.memory.clone(),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            halted: self.halted,
+            journal: self.journal.as_ref().map(|journal| Journal::new(journal.capacity())),
+            code_tracker: self.code_tracker.clone(),
+            coverage: self.coverage.clone(),
+            protection: self.protection.clone(),
+            fault: self.fault,
+            isa: self.isa,
+            crash_recorder: self.crash_recorder.clone(),
+            catch_faults: self.catch_faults,
+        }
     }
 }
 
 impl From<&[i64]> for Program {
     fn from(prog: &[i64]) -> Program {
+        let image = Memory::from(prog);
         Program {
-            default_prog: prog.to_vec().into_boxed_slice(),
-            prog: {
-                let mut prog_vec = prog.to_vec();
-                prog_vec.extend(std::iter::repeat(0).take(3));
-                prog_vec
-            },
+            image: image.clone(),
+            memory: image,
             pc: 0,
             relative_base: 0,
             halted: false,
+            journal: None,
+            code_tracker: None,
+            coverage: None,
+            protection: None,
+            fault: None,
+            isa: Isa::Full,
+            crash_recorder: crashdump::scoped_recorder(),
+            catch_faults: false,
         }
     }
 }
@@ -299,16 +559,10 @@ impl From<&[i64]> for Program {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::day5;
-    use crate::day7;
-    use crate::day9;
+    use super::loader::load_program;
 
     fn read_intcode_input(path: &str) -> Vec<i64> {
-        let input = std::fs::read_to_string(path).unwrap();
-        input
-            .split(',')
-            .flat_map(|num_str| num_str.trim().parse::<i64>())
-            .collect()
+        load_program(path).unwrap()
     }
 
     #[test]
@@ -316,47 +570,48 @@ mod tests {
         let input = read_intcode_input("inputs/2019/day2.txt");
         let mut prog = Program::from(&input[..]);
         {
-            let prog_content = prog.prog_mut();
-            prog_content[1] = 12;
-            prog_content[2] = 2;
+            let memory = prog.memory_mut();
+            memory.set(1, 12);
+            memory.set(2, 2);
         }
         prog.run(|_| { IOReturn::Input(0) });
-        assert_eq!(prog.prog()[0], 6327510);
+        assert_eq!(prog.memory()[0], 6327510);
     }
 
     #[test]
-    fn day5_part1() {
-        let prog = read_intcode_input("inputs/2019/day5.txt");
-        assert_eq!(day5::part1(prog), 16434972);
-    }
-
-    #[test]
-    fn day5_part2() {
-        let prog = read_intcode_input("inputs/2019/day5.txt");
-        assert_eq!(day5::part2(prog), 16694270);
-    }
-
-    #[test]
-    fn day7_part1() {
-        let prog = read_intcode_input("inputs/2019/day7.txt");
-        assert_eq!(day7::part1(prog), 359142);
-    }
-
-    #[test]
-    fn day7_part2() {
-        let prog = read_intcode_input("inputs/2019/day7.txt");
-        assert_eq!(day7::part2(prog), 4374895);
-    }
-
-    #[test]
-    fn day9_part1() {
-        let prog = read_intcode_input("inputs/2019/day9.txt");
-        assert_eq!(day9::part1(prog), 3235019597);
-    }
+    fn io_handler_sees_machine_state() {
+        // IN [rb+5]; OUT [rb+5] after moving the relative base, so the handler sees both registers change.
+        let image = [109, 10, 203, 5, 109, -3, 204, 8, 99];
+        let mut prog = Program::from(&image[..]);
+        let mut io_log = Vec::new();
+        prog.run_with_machine(|io_op, machine| {
+            io_log.push((machine.pc(), machine.relative_base(), machine.memory()[machine.pc()]));
+            match io_op {
+                IOOperation::Input => IOReturn::Input(42),
+                IOOperation::Output(value) => {
+                    assert_eq!(machine.memory()[15], value);
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+            }
+        });
+        assert_eq!(io_log, vec![(2, 10, 203), (6, 7, 204)]);
 
-    #[test]
-    fn day9_part2() {
-        let prog = read_intcode_input("inputs/2019/day9.txt");
-        assert_eq!(day9::part2(prog), 80274);
+        // Every day 5 input request comes from the same IN instruction near the start of the program.
+        let input = read_intcode_input("inputs/2019/day5.txt");
+        let mut prog = Program::from(&input[..]);
+        let mut input_pcs = Vec::new();
+        while !prog.is_halted() {
+            prog.step_with_machine(|io_op, machine| {
+                match io_op {
+                    IOOperation::Input => {
+                        input_pcs.push(machine.pc());
+                        IOReturn::Input(5)
+                    },
+                    IOOperation::Output(_) => IOReturn::Output(ExecuteAction::Continue),
+                }
+            });
+        }
+        assert_eq!(input_pcs.len(), 1);
+        assert_eq!(input[input_pcs[0]] % 100, 3);
     }
 }
@@ -0,0 +1,771 @@
+//! A compiler for a tiny C-like language that targets Intcode.
+//!
+//! ```text
+//! fn square(x) { return x * x; }
+//! fn main() {
+//!     let n = input();
+//!     while (n > 0) {
+//!         output(square(n));
+//!         n = n - 1;
+//!     }
+//! }
+//! ```
+//!
+//! Values are integers. Statements are `let`, assignment, `if`/`else`, `while`, `return` and expression statements.
+//! Expressions support `+ - *`, comparisons, `== !=`, `&& || !`, unary minus, calls, `input()` and `output(x)`.
+//! Execution starts at `main`. `&&` and `||` evaluate both sides.
+//!
+//! Every function gets a frame addressed through the relative base. `[rb+0]` holds the return address, the parameters
+//! follow, then locals, then temporaries. A call places the callee's frame just above the caller's live slots, moves
+//! the relative base up to it, and moves it back down after the callee returns its value in `[rb+1]`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Compiles `source` into an Intcode image runnable by `Program`.
+#[allow(dead_code)]
+pub fn compile(source: &str) -> Result<Vec<i64>, CompileError> {
+    let tokens = tokenize(source)?;
+    let functions = Parser { tokens, pos: 0 }.parse_program()?;
+    CodeGen::default().generate(&functions)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(i64),
+    Ident(String),
+    Sym(&'static str),
+    Eof,
+}
+
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||",
+    "+", "-", "*", "<", ">", "!", "=", "(", ")", "{", "}", ",", ";",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Tok, usize)>, CompileError> {
+    let mut tokens = Vec::new();
+    for (line_idx, line) in source.lines().enumerate() {
+        let line_num = line_idx + 1;
+        let line = match line.find("//") {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+        let mut rest = line.trim_start();
+        while !rest.is_empty() {
+            let first = rest.chars().next().unwrap();
+            let token_len = if first.is_ascii_digit() {
+                let len = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+                let value = rest[..len].parse::<i64>().map_err(|_| CompileError {
+                    line: line_num,
+                    message: format!("number {} is too large", &rest[..len]),
+                })?;
+                tokens.push((Tok::Num(value), line_num));
+                len
+            }
+            else if first.is_alphabetic() || first == '_' {
+                let len = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+                tokens.push((Tok::Ident(rest[..len].to_string()), line_num));
+                len
+            }
+            else {
+                match SYMBOLS.iter().find(|sym| rest.starts_with(*sym)) {
+                    Some(sym) => {
+                        tokens.push((Tok::Sym(sym), line_num));
+                        sym.len()
+                    },
+                    None => return Err(CompileError { line: line_num, message: format!("unexpected character {:?}", first) }),
+                }
+            };
+            rest = rest[token_len..].trim_start();
+        }
+    }
+    let last_line = tokens.last().map_or(1, |&(_, line)| line);
+    tokens.push((Tok::Eof, last_line));
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(i64),
+    Var(String),
+    Input,
+    Call(String, Vec<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr),
+    If(Expr, Block, Block),
+    While(Expr, Block),
+    Return(Expr),
+    Output(Expr),
+    Expr(Expr),
+    Block(Block),
+}
+
+/// Statements paired with the line they start on.
+type Block = Vec<(Stmt, usize)>;
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Block,
+    line: usize,
+}
+
+struct Parser {
+    tokens: Vec<(Tok, usize)>,
+    pos: usize,
+}
+
+/// Binary operators by precedence level, loosest first.
+const PRECEDENCE: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!="],
+    &["<", ">", "<=", ">="],
+    &["+", "-"],
+    &["*"],
+];
+
+impl Parser {
+    fn peek(&self) -> &Tok {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Tok {
+        let tok = self.tokens[self.pos].0.clone();
+        if tok != Tok::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, CompileError> {
+        Err(CompileError { line: self.line(), message })
+    }
+
+    fn eat(&mut self, sym: &str) -> bool {
+        if matches!(self.peek(), Tok::Sym(next_sym) if *next_sym == sym) {
+            self.pos += 1;
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), CompileError> {
+        if self.eat(sym) {
+            Ok(())
+        }
+        else {
+            self.error(format!("expected `{}`, found {}", sym, describe(self.peek())))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.next() {
+            Tok::Ident(name) => Ok(name),
+            tok => {
+                self.pos -= (tok != Tok::Eof) as usize;
+                self.error(format!("expected a name, found {}", describe(&tok)))
+            },
+        }
+    }
+
+    fn parse_program(&mut self) -> Result<Vec<Function>, CompileError> {
+        let mut functions = Vec::new();
+        while *self.peek() != Tok::Eof {
+            let line = self.line();
+            match self.ident()?.as_str() {
+                "fn" => (),
+                other => return Err(CompileError { line, message: format!("expected `fn`, found `{}`", other) }),
+            }
+            let name = self.ident()?;
+            self.expect("(")?;
+            let mut params = Vec::new();
+            if !self.eat(")") {
+                loop {
+                    params.push(self.ident()?);
+                    if self.eat(")") {
+                        break;
+                    }
+                    self.expect(",")?;
+                }
+            }
+            let body = self.block()?;
+            functions.push(Function { name, params, body, line });
+        }
+        Ok(functions)
+    }
+
+    fn block(&mut self) -> Result<Block, CompileError> {
+        self.expect("{")?;
+        let mut stmts = Vec::new();
+        while !self.eat("}") {
+            if *self.peek() == Tok::Eof {
+                return self.error(String::from("unexpected end of input, expected `}`"));
+            }
+            let line = self.line();
+            stmts.push((self.statement()?, line));
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        if *self.peek() == Tok::Sym("{") {
+            return Ok(Stmt::Block(self.block()?));
+        }
+        let keyword = match self.peek() {
+            Tok::Ident(name) => name.clone(),
+            _ => String::new(),
+        };
+        let stmt = match keyword.as_str() {
+            "let" => {
+                self.next();
+                let name = self.ident()?;
+                self.expect("=")?;
+                Stmt::Let(name, self.expr()?)
+            },
+            "if" => {
+                self.next();
+                self.expect("(")?;
+                let cond = self.expr()?;
+                self.expect(")")?;
+                let then_body = self.block()?;
+                let else_body = if *self.peek() == Tok::Ident(String::from("else")) {
+                    self.next();
+                    if *self.peek() == Tok::Ident(String::from("if")) {
+                        let line = self.line();
+                        vec![(self.statement()?, line)]
+                    }
+                    else {
+                        self.block()?
+                    }
+                }
+                else {
+                    Vec::new()
+                };
+                return Ok(Stmt::If(cond, then_body, else_body));
+            },
+            "while" => {
+                self.next();
+                self.expect("(")?;
+                let cond = self.expr()?;
+                self.expect(")")?;
+                return Ok(Stmt::While(cond, self.block()?));
+            },
+            "return" => {
+                self.next();
+                if *self.peek() == Tok::Sym(";") {
+                    Stmt::Return(Expr::Num(0))
+                }
+                else {
+                    Stmt::Return(self.expr()?)
+                }
+            },
+            "output" => {
+                self.next();
+                self.expect("(")?;
+                let value = self.expr()?;
+                self.expect(")")?;
+                Stmt::Output(value)
+            },
+            _ => {
+                let is_assign = matches!(self.tokens.get(self.pos + 1), Some((Tok::Sym("="), _)));
+                if is_assign {
+                    let name = self.ident()?;
+                    self.expect("=")?;
+                    Stmt::Assign(name, self.expr()?)
+                }
+                else {
+                    Stmt::Expr(self.expr()?)
+                }
+            },
+        };
+        self.expect(";")?;
+        Ok(stmt)
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        loop {
+            let op = match self.peek() {
+                Tok::Sym(sym) if PRECEDENCE[level].contains(sym) => *sym,
+                _ => return Ok(lhs),
+            };
+            self.next();
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.eat("-") {
+            return Ok(match self.unary()? {
+                Expr::Num(value) => Expr::Num(-value),
+                expr => Expr::Neg(Box::new(expr)),
+            });
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        match self.next() {
+            Tok::Num(value) => Ok(Expr::Num(value)),
+            Tok::Sym("(") => {
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Tok::Ident(name) => {
+                if !self.eat("(") {
+                    return Ok(Expr::Var(name));
+                }
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                if name == "input" {
+                    if !args.is_empty() {
+                        return self.error(String::from("`input` takes no arguments"));
+                    }
+                    return Ok(Expr::Input);
+                }
+                Ok(Expr::Call(name, args))
+            },
+            tok => {
+                self.pos -= (tok != Tok::Eof) as usize;
+                self.error(format!("expected an expression, found {}", describe(&tok)))
+            },
+        }
+    }
+}
+
+fn describe(tok: &Tok) -> String {
+    match tok {
+        Tok::Num(value) => format!("`{}`", value),
+        Tok::Ident(name) => format!("`{}`", name),
+        Tok::Sym(sym) => format!("`{}`", sym),
+        Tok::Eof => String::from("end of input"),
+    }
+}
+
+/// An instruction operand: an immediate value or a slot in the current frame.
+#[derive(Debug, Copy, Clone)]
+enum Operand {
+    Imm(i64),
+    Slot(usize),
+}
+
+impl Operand {
+    fn mode(self) -> i64 {
+        match self {
+            Operand::Imm(_) => 1,
+            Operand::Slot(_) => 2,
+        }
+    }
+
+    fn word(self) -> Word {
+        match self {
+            Operand::Imm(value) => Word::Value(value),
+            Operand::Slot(slot) => Word::Value(slot as i64),
+        }
+    }
+}
+
+/// An output word, possibly referring to a label whose address isn't known yet.
+#[derive(Debug, Copy, Clone)]
+enum Word {
+    Value(i64),
+    Label(usize),
+}
+
+#[derive(Default)]
+struct CodeGen {
+    code: Vec<Word>,
+    labels: Vec<Option<usize>>,
+    functions: HashMap<String, (usize, usize)>,
+    /// Name to slot maps, innermost scope last.
+    scopes: Vec<HashMap<String, usize>>,
+    /// The first frame slot not holding a parameter or live local.
+    next_slot: usize,
+    line: usize,
+}
+
+impl CodeGen {
+    fn error<T>(&self, message: String) -> Result<T, CompileError> {
+        Err(CompileError { line: self.line, message })
+    }
+
+    fn new_label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place(&mut self, label: usize) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn emit(&mut self, opcode: i64, operands: &[Operand]) {
+        let modes = operands.iter().enumerate().map(|(idx, operand)| operand.mode() * 10_i64.pow(idx as u32 + 2)).sum::<i64>();
+        self.code.push(Word::Value(opcode + modes));
+        self.code.extend(operands.iter().map(|operand| operand.word()));
+    }
+
+    fn emit_jump(&mut self, opcode: i64, cond: Operand, label: usize) {
+        self.code.push(Word::Value(opcode + cond.mode() * 100 + 1000));
+        self.code.push(cond.word());
+        self.code.push(Word::Label(label));
+    }
+
+    fn emit_move(&mut self, from: Operand, target: usize) {
+        if let Operand::Slot(slot) = from {
+            if slot == target {
+                return;
+            }
+        }
+        self.emit(1, &[from, Operand::Imm(0), Operand::Slot(target)]);
+    }
+
+    fn lookup(&self, name: &str) -> Result<usize, CompileError> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(&slot) => Ok(slot),
+            None => self.error(format!("unknown variable `{}`", name)),
+        }
+    }
+
+    fn generate(mut self, functions: &[Function]) -> Result<Vec<i64>, CompileError> {
+        for function in functions {
+            self.line = function.line;
+            let label = self.new_label();
+            if self.functions.insert(function.name.clone(), (label, function.params.len())).is_some() {
+                return self.error(format!("function `{}` is defined twice", function.name));
+            }
+        }
+        let main_label = match self.functions.get("main") {
+            Some(&(label, 0)) => label,
+            Some(_) => return self.error(String::from("`main` must not take parameters")),
+            None => return self.error(String::from("no `main` function")),
+        };
+
+        // Entry: point the relative base at the stack (just past the code), then call main with its return address on halt.
+        let stack_label = self.new_label();
+        let halt_label = self.new_label();
+        self.code.extend(&[Word::Value(109), Word::Label(stack_label)]);
+        self.code.extend(&[Word::Value(21101), Word::Label(halt_label), Word::Value(0), Word::Value(0)]);
+        self.emit_jump(5, Operand::Imm(1), main_label);
+        self.place(halt_label);
+        self.emit(99, &[]);
+
+        for function in functions {
+            self.function(function)?;
+        }
+        self.place(stack_label);
+
+        let labels = &self.labels;
+        Ok(self.code
+            .iter()
+            .map(|word| match *word {
+                Word::Value(value) => value,
+                Word::Label(label) => labels[label].expect("label was never placed") as i64,
+            })
+            .collect())
+    }
+
+    fn function(&mut self, function: &Function) -> Result<(), CompileError> {
+        self.line = function.line;
+        let (label, _) = self.functions[&function.name];
+        self.place(label);
+        let mut params = HashMap::new();
+        for (param_idx, param) in function.params.iter().enumerate() {
+            if params.insert(param.clone(), param_idx + 1).is_some() {
+                return self.error(format!("parameter `{}` is declared twice", param));
+            }
+        }
+        self.scopes = vec![params];
+        self.next_slot = function.params.len() + 1;
+        self.block(&function.body)?;
+        // Falling off the end returns 0.
+        self.stmt(&Stmt::Return(Expr::Num(0)))
+    }
+
+    fn block(&mut self, stmts: &[(Stmt, usize)]) -> Result<(), CompileError> {
+        let saved_slot = self.next_slot;
+        self.scopes.push(HashMap::new());
+        for (stmt, line) in stmts {
+            self.line = *line;
+            self.stmt(stmt)?;
+        }
+        self.scopes.pop();
+        self.next_slot = saved_slot;
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Let(name, value) => {
+                let slot = self.next_slot;
+                self.expr(value, slot, slot + 1)?;
+                self.next_slot += 1;
+                self.scopes.last_mut().unwrap().insert(name.clone(), slot);
+            },
+            Stmt::Assign(name, value) => {
+                let slot = self.lookup(name)?;
+                let scratch = self.next_slot;
+                self.expr(value, slot, scratch)?;
+            },
+            Stmt::If(cond, then_body, else_body) => {
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+                let cond = self.operand(cond, self.next_slot)?;
+                self.emit_jump(6, cond, else_label);
+                self.block(then_body)?;
+                self.emit_jump(5, Operand::Imm(1), end_label);
+                self.place(else_label);
+                self.block(else_body)?;
+                self.place(end_label);
+            },
+            Stmt::While(cond, body) => {
+                let top_label = self.new_label();
+                let end_label = self.new_label();
+                self.place(top_label);
+                let cond = self.operand(cond, self.next_slot)?;
+                self.emit_jump(6, cond, end_label);
+                self.block(body)?;
+                self.emit_jump(5, Operand::Imm(1), top_label);
+                self.place(end_label);
+            },
+            Stmt::Return(value) => {
+                let value = self.operand(value, self.next_slot)?;
+                self.emit_move(value, 1);
+                // Jump to the return address in [rb+0].
+                self.code.extend(&[Word::Value(2106), Word::Value(0), Word::Value(0)]);
+            },
+            Stmt::Output(value) => {
+                let value = self.operand(value, self.next_slot)?;
+                self.emit(4, &[value]);
+            },
+            Stmt::Expr(value) => {
+                let scratch = self.next_slot;
+                self.expr(value, scratch, scratch + 1)?;
+            },
+            Stmt::Block(body) => self.block(body)?,
+        }
+        Ok(())
+    }
+
+    /// Returns an operand for `expr`, computing it into slot `scratch` if it isn't a literal or variable.
+    /// Slots from `scratch` up may be clobbered.
+    fn operand(&mut self, expr: &Expr, scratch: usize) -> Result<Operand, CompileError> {
+        match expr {
+            Expr::Num(value) => Ok(Operand::Imm(*value)),
+            Expr::Var(name) => Ok(Operand::Slot(self.lookup(name)?)),
+            _ => {
+                self.expr(expr, scratch, scratch + 1)?;
+                Ok(Operand::Slot(scratch))
+            },
+        }
+    }
+
+    /// Computes `expr` into slot `target`. Slots from `scratch` up may be clobbered.
+    fn expr(&mut self, expr: &Expr, target: usize, scratch: usize) -> Result<(), CompileError> {
+        match expr {
+            Expr::Num(_) | Expr::Var(_) => {
+                let value = self.operand(expr, scratch)?;
+                self.emit_move(value, target);
+            },
+            Expr::Input => self.emit(3, &[Operand::Slot(target)]),
+            Expr::Call(name, args) => {
+                let (label, param_count) = match self.functions.get(name) {
+                    Some(&function) => function,
+                    None => return self.error(format!("unknown function `{}`", name)),
+                };
+                if args.len() != param_count {
+                    return self.error(format!("`{}` takes {} arguments but {} were given", name, param_count, args.len()));
+                }
+                // The callee's frame starts at `scratch`.
+                let frame = scratch;
+                for (arg_idx, arg) in args.iter().enumerate() {
+                    self.expr(arg, frame + 1 + arg_idx, frame + 1 + args.len())?;
+                }
+                let return_label = self.new_label();
+                self.code.extend(&[Word::Value(21101), Word::Label(return_label), Word::Value(0), Word::Value(frame as i64)]);
+                self.emit(9, &[Operand::Imm(frame as i64)]);
+                self.emit_jump(5, Operand::Imm(1), label);
+                self.place(return_label);
+                self.emit(9, &[Operand::Imm(-(frame as i64))]);
+                self.emit_move(Operand::Slot(frame + 1), target);
+            },
+            Expr::Neg(value) => {
+                let value = self.operand(value, scratch)?;
+                self.emit(2, &[value, Operand::Imm(-1), Operand::Slot(target)]);
+            },
+            Expr::Not(value) => {
+                let value = self.operand(value, scratch)?;
+                self.emit(8, &[value, Operand::Imm(0), Operand::Slot(target)]);
+            },
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.operand(lhs, scratch)?;
+                let rhs = self.operand(rhs, scratch + 1)?;
+                let target_slot = Operand::Slot(target);
+                match *op {
+                    "+" => self.emit(1, &[lhs, rhs, target_slot]),
+                    "*" => self.emit(2, &[lhs, rhs, target_slot]),
+                    "-" => match rhs {
+                        Operand::Imm(value) => self.emit(1, &[lhs, Operand::Imm(-value), target_slot]),
+                        Operand::Slot(_) => {
+                            let negated = scratch + 2;
+                            self.emit(2, &[rhs, Operand::Imm(-1), Operand::Slot(negated)]);
+                            self.emit(1, &[lhs, Operand::Slot(negated), target_slot]);
+                        },
+                    },
+                    "<" => self.emit(7, &[lhs, rhs, target_slot]),
+                    ">" => self.emit(7, &[rhs, lhs, target_slot]),
+                    "==" => self.emit(8, &[lhs, rhs, target_slot]),
+                    "<=" | ">=" | "!=" => {
+                        let (opcode, first, second) = match *op {
+                            "<=" => (7, rhs, lhs),
+                            ">=" => (7, lhs, rhs),
+                            _ => (8, lhs, rhs),
+                        };
+                        // Compute the opposite comparison, then negate it.
+                        let opposite = scratch + 2;
+                        self.emit(opcode, &[first, second, Operand::Slot(opposite)]);
+                        self.emit(8, &[Operand::Slot(opposite), Operand::Imm(0), target_slot]);
+                    },
+                    "&&" | "||" => {
+                        // With both sides turned into "is zero" flags, `a && b` is "neither flag set" and `a || b` is "not both set".
+                        let (lhs_zero, rhs_zero) = (scratch + 2, scratch + 3);
+                        self.emit(8, &[lhs, Operand::Imm(0), Operand::Slot(lhs_zero)]);
+                        self.emit(8, &[rhs, Operand::Imm(0), Operand::Slot(rhs_zero)]);
+                        let combine = if *op == "&&" { 1 } else { 2 };
+                        self.emit(combine, &[Operand::Slot(lhs_zero), Operand::Slot(rhs_zero), Operand::Slot(lhs_zero)]);
+                        self.emit(8, &[Operand::Slot(lhs_zero), Operand::Imm(0), target_slot]);
+                    },
+                    _ => unreachable!("operator `{}` has no code generation", op),
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::prelude::*;
+
+    fn compile_and_run(source: &str, inputs: &[i64]) -> Vec<i64> {
+        let image = compile(source).unwrap();
+        let mut prog = Program::from(image.as_slice());
+        let outputs = prog.run_with_inputs(inputs);
+        assert!(prog.is_halted());
+        outputs
+    }
+
+    #[test]
+    fn compiler_arithmetic_and_control_flow() {
+        let source = "
+            // Sum of 1..=n, then a countdown of odd numbers.
+            fn main() {
+                let n = input();
+                let sum = 0;
+                let i = 1;
+                while (i <= n) {
+                    sum = sum + i;
+                    i = i + 1;
+                }
+                output(sum);
+                while (n > 0) {
+                    if (n - (n + 1) * 0 == 2 * (n - n) + n && !(n == 2 * 2)) {
+                        output(-n * 3 - 1);
+                    } else if (n == 4 || n > 100) {
+                        output(0);
+                    }
+                    n = n - 1;
+                }
+            }
+        ";
+        assert_eq!(compile_and_run(source, &[5]), vec![15, -16, 0, -10, -7, -4]);
+        assert_eq!(compile_and_run(source, &[0]), vec![0]);
+    }
+
+    #[test]
+    fn compiler_functions_and_recursion() {
+        let source = "
+            fn fib(n) {
+                if (n < 2) { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+            fn max3(a, b, c) {
+                let m = a;
+                if (b > m) { m = b; }
+                if (c > m) { m = c; }
+                return m;
+            }
+            fn nothing() {}
+            fn main() {
+                output(fib(input()));
+                output(max3(fib(5), 2 * max3(1, 3, 2), -7));
+                output(nothing());
+                let x = 10;
+                { let x = 20; output(x); }
+                output(x >= 10);
+                output(x != 10);
+            }
+        ";
+        assert_eq!(compile_and_run(source, &[15]), vec![610, 6, 0, 20, 1, 0]);
+    }
+
+    #[test]
+    fn compiler_reports_errors() {
+        let error = compile("fn main() {\n    output(y);\n}").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("unknown variable `y`"));
+        assert!(compile("fn f(a) { return a; }\nfn main() { output(f()); }").is_err());
+        assert!(compile("fn helper() {}").is_err());
+        assert_eq!(compile("fn main() {\n let x = 1 $ 2; }").unwrap_err().line, 2);
+    }
+}
@@ -0,0 +1,208 @@
+//! Conformance tests built from the published examples for days 2, 5 and 9, plus edge cases the examples don't cover.
+//! Every case runs against every execution engine in `ENGINES`.
+
+use super::prelude::*;
+use super::isa::Isa;
+use super::protect::Fault;
+
+type Engine = fn(&Program, &[i64]) -> (Program, Vec<i64>);
+
+fn engine_run(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    let outputs = prog.run_with_inputs(inputs);
+    (prog, outputs)
+}
+
+fn engine_step(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    let mut input_iter = inputs.iter().copied();
+    let mut outputs = Vec::new();
+    while !prog.is_halted() {
+        let exec_action = prog.step(|io_op| {
+            match io_op {
+                IOOperation::Input => match input_iter.next() {
+                    Some(value) => IOReturn::Input(value),
+                    None => IOReturn::Output(ExecuteAction::Break),
+                },
+                IOOperation::Output(value) => {
+                    outputs.push(value);
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+            }
+        });
+        if let ExecuteAction::Break = exec_action {
+            break;
+        }
+    }
+    (prog, outputs)
+}
+
+fn engine_journaled(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    prog.enable_journal(16);
+    let outputs = prog.run_with_inputs(inputs);
+    (prog, outputs)
+}
+
+fn engine_code_tracked(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    prog.enable_code_tracking(false);
+    let outputs = prog.run_with_inputs(inputs);
+    (prog, outputs)
+}
+
+fn engine_fork(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut fork = prog.fork();
+    let outputs = fork.run_with_inputs(inputs);
+    (fork, outputs)
+}
+
+fn engine_coverage(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    prog.enable_coverage();
+    let outputs = prog.run_with_inputs(inputs);
+    (prog, outputs)
+}
+
+/// Protects regions far above anything the cases touch, so every instruction is checked but none faults.
+fn engine_protected(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    prog.protect_read_only(1 << 40..1 << 41);
+    prog.protect_no_execute(1 << 41..usize::MAX);
+    let outputs = prog.run_with_inputs(inputs);
+    (prog, outputs)
+}
+
+/// Runs under the earliest instruction set profile the case doesn't fault under.
+fn engine_isa(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    for &isa in &[Isa::Day2, Isa::Day5] {
+        let mut restricted = prog.clone();
+        restricted.set_isa(isa);
+        restricted.catch_faults();
+        let outputs = restricted.run_with_inputs(inputs);
+        if !matches!(restricted.fault(), Some(Fault::Unsupported { .. })) {
+            return (restricted, outputs);
+        }
+    }
+    engine_run(prog, inputs)
+}
+
+fn engine_crash_recorded(prog: &Program, inputs: &[i64]) -> (Program, Vec<i64>) {
+    let mut prog = prog.clone();
+    prog.enable_crash_dumps(std::env::temp_dir().join("aoc2019-conformance-dumps"), 16);
+    let outputs = prog.run_with_inputs(inputs);
+    (prog, outputs)
+}
+
+const ENGINES: &[(&str, Engine)] = &[
+    ("run", engine_run),
+    ("step", engine_step),
+    ("journaled", engine_journaled),
+    ("code tracked", engine_code_tracked),
+    ("fork", engine_fork),
+    ("coverage", engine_coverage),
+    ("protected", engine_protected),
+    ("isa", engine_isa),
+    ("crash recorded", engine_crash_recorded),
+];
+
+/// Runs `image` with `inputs` on every engine and checks that each one halts with the expected outputs.
+/// Returns the final machine from each engine so callers can check memory.
+fn check_outputs(image: &[i64], inputs: &[i64], expected: &[i64]) -> Vec<Program> {
+    let prog = Program::from(image);
+    ENGINES
+        .iter()
+        .map(|(name, engine)| {
+            let (prog, outputs) = engine(&prog, inputs);
+            assert!(prog.is_halted(), "engine `{}` did not halt on {:?}", name, image);
+            assert_eq!(outputs, expected, "engine `{}` on {:?} with inputs {:?}", name, image, inputs);
+            prog
+        })
+        .collect()
+}
+
+fn check_memory(image: &[i64], expected: &[i64]) {
+    for (prog, (name, _)) in check_outputs(image, &[], &[]).iter().zip(ENGINES) {
+        assert_eq!(prog.memory().to_vec(), expected, "engine `{}` on {:?}", name, image);
+    }
+}
+
+#[test]
+fn day2_examples() {
+    check_memory(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50], &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]);
+    check_memory(&[1, 0, 0, 0, 99], &[2, 0, 0, 0, 99]);
+    check_memory(&[2, 3, 0, 3, 99], &[2, 3, 0, 6, 99]);
+    check_memory(&[2, 4, 4, 5, 99, 0], &[2, 4, 4, 5, 99, 9801]);
+    check_memory(&[1, 1, 1, 4, 99, 5, 6, 0, 99], &[30, 1, 1, 4, 2, 5, 6, 0, 99]);
+}
+
+#[test]
+fn day5_io_and_modes() {
+    for &value in &[0, 1, -7, 123456789] {
+        check_outputs(&[3, 0, 4, 0, 99], &[value], &[value]);
+    }
+    check_memory(&[1002, 4, 3, 4, 33], &[1002, 4, 3, 4, 99]);
+    check_memory(&[1101, 100, -1, 4, 0], &[1101, 100, -1, 4, 99]);
+}
+
+#[test]
+fn day5_comparisons() {
+    let equal_position = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    let less_position = [3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+    let equal_immediate = [3, 3, 1108, -1, 8, 3, 4, 3, 99];
+    let less_immediate = [3, 3, 1107, -1, 8, 3, 4, 3, 99];
+    for &input in &[-8, 0, 7, 8, 9] {
+        check_outputs(&equal_position, &[input], &[(input == 8) as i64]);
+        check_outputs(&less_position, &[input], &[(input < 8) as i64]);
+        check_outputs(&equal_immediate, &[input], &[(input == 8) as i64]);
+        check_outputs(&less_immediate, &[input], &[(input < 8) as i64]);
+    }
+}
+
+#[test]
+fn day5_jumps() {
+    let jump_position = [3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+    let jump_immediate = [3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+    let compare_to_8 = [
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+        1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+        999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+    ];
+    for &input in &[-3, 0, 1, 7, 8, 9, 100] {
+        check_outputs(&jump_position, &[input], &[(input != 0) as i64]);
+        check_outputs(&jump_immediate, &[input], &[(input != 0) as i64]);
+        check_outputs(&compare_to_8, &[input], &[999 + (input >= 8) as i64 + (input > 8) as i64]);
+    }
+}
+
+#[test]
+fn day9_examples() {
+    let quine = [109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+    check_outputs(&quine, &[], &quine);
+    check_outputs(&[1102, 34915192, 34915192, 7, 4, 7, 99, 0], &[], &[1219070632396864]);
+    check_outputs(&[104, 1125899906842624, 99], &[], &[1125899906842624]);
+}
+
+#[test]
+fn relative_mode_writes() {
+    // Add into relative address 0 with the base at 10, then output it back through relative mode.
+    check_outputs(&[109, 10, 21101, 3, 4, 0, 204, 0, 99], &[], &[7]);
+    // Negative relative offsets and relative-mode input.
+    check_outputs(&[109, 20, 21101, 1, 2, -5, 4, 15, 99], &[], &[3]);
+    check_outputs(&[109, 7, 203, 5, 204, 5, 99], &[42], &[42]);
+    // The base accumulates across adjustments, including negative ones.
+    check_outputs(&[109, 50, 109, -45, 204, -1, 99], &[], &[204]);
+}
+
+#[test]
+fn memory_growth() {
+    // Reads past the image see zero, and writes far past it grow memory.
+    check_outputs(&[4, 100000, 99], &[], &[0]);
+    for prog in check_outputs(&[1101, 5, 6, 5000, 4, 5000, 99], &[], &[11]) {
+        assert_eq!(prog.memory().len(), 5001);
+        assert_eq!(prog.memory()[4999], 0);
+    }
+    // Execution can run off the image into memory the program wrote itself.
+    check_outputs(&[1101, 0, 99, 9, 1105, 1, 9, 0, 0], &[], &[]);
+    check_outputs(&[1101, 4, 0, 3000, 1101, 3000, 0, 3001, 1101, 99, 0, 3002, 1105, 1, 3000], &[], &[4]);
+}
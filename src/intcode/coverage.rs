@@ -0,0 +1,278 @@
+use super::Program;
+use super::disasm::{self, DisasmLine};
+use super::memory::Memory;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const EXECUTED: u8 = 1;
+const READ: u8 = 2;
+const WRITTEN: u8 = 4;
+
+/// Which ways a conditional jump has gone.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct BranchCoverage {
+    pub taken: bool,
+    pub not_taken: bool,
+}
+
+impl BranchCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.taken && self.not_taken
+    }
+}
+
+impl fmt::Display for BranchCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.taken, self.not_taken) {
+            (true, true) => write!(f, "taken and not taken"),
+            (true, false) => write!(f, "only taken"),
+            (false, true) => write!(f, "never taken"),
+            (false, false) => write!(f, "never reached"),
+        }
+    }
+}
+
+/// Records instruction starts, data accesses and branch directions. Coverage accumulates across `Program::reset`,
+/// so several runs of the same program can be combined; `merge` combines coverage from forks or other programs.
+///
+/// Cells are stored sparsely, since a wrapped relative address can land anywhere in the address space.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    cells: BTreeMap<usize, u8>,
+    branches: BTreeMap<usize, BranchCoverage>,
+}
+
+impl Coverage {
+    fn mark(&mut self, addr: usize, flag: u8) {
+        *self.cells.entry(addr).or_default() |= flag;
+    }
+
+    fn flags(&self, addr: usize) -> u8 {
+        self.cells.get(&addr).copied().unwrap_or(0)
+    }
+
+    pub(super) fn mark_executed(&mut self, pc: usize) {
+        self.mark(pc, EXECUTED);
+    }
+
+    pub(super) fn mark_read(&mut self, addr: usize) {
+        self.mark(addr, READ);
+    }
+
+    pub(super) fn mark_written(&mut self, addr: usize) {
+        self.mark(addr, WRITTEN);
+    }
+
+    pub(super) fn mark_branch(&mut self, pc: usize, taken: bool) {
+        let branch = self.branches.entry(pc).or_default();
+        if taken {
+            branch.taken = true;
+        } else {
+            branch.not_taken = true;
+        }
+    }
+
+    /// Whether an instruction started at `addr`.
+    pub fn was_executed(&self, addr: usize) -> bool {
+        self.flags(addr) & EXECUTED != 0
+    }
+
+    pub fn was_read(&self, addr: usize) -> bool {
+        self.flags(addr) & READ != 0
+    }
+
+    pub fn was_written(&self, addr: usize) -> bool {
+        self.flags(addr) & WRITTEN != 0
+    }
+
+    pub fn branch(&self, pc: usize) -> Option<BranchCoverage> {
+        self.branches.get(&pc).copied()
+    }
+
+    /// Every conditional jump executed so far, by address.
+    #[allow(dead_code)]
+    pub fn branches(&self) -> impl Iterator<Item = (usize, BranchCoverage)> + '_ {
+        self.branches.iter().map(|(&pc, &branch)| (pc, branch))
+    }
+
+    #[allow(dead_code)]
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&addr, &flags) in &other.cells {
+            self.mark(addr, flags);
+        }
+        for (pc, branch) in other.branches() {
+            let merged = self.branches.entry(pc).or_default();
+            merged.taken |= branch.taken;
+            merged.not_taken |= branch.not_taken;
+        }
+    }
+
+    /// Sweeps `memory` below `end`, decoding at every executed address and treating cells only ever accessed as data as data.
+    fn listing(&self, memory: &Memory, end: usize) -> Vec<DisasmLine> {
+        let mut lines = Vec::new();
+        let mut addr = 0;
+        while addr < end {
+            let is_data = !self.was_executed(addr) && self.flags(addr) & (READ | WRITTEN) != 0;
+            let line = if is_data {
+                DisasmLine { addr, words: vec![memory.get(addr)], text: format!("DATA {}", memory.get(addr)), is_instruction: false }
+            } else {
+                disasm::disassemble_one(memory, addr)
+            };
+            addr += line.words.len();
+            lines.push(line);
+        }
+        lines
+    }
+
+    pub fn summary(&self, memory: &Memory, end: usize) -> CoverageSummary {
+        let mut summary = CoverageSummary::default();
+        for line in self.listing(memory, end).iter().filter(|line| line.is_instruction) {
+            summary.instructions += 1;
+            if self.was_executed(line.addr) {
+                summary.executed += 1;
+            }
+        }
+        summary.branches = self.branches.len();
+        summary.complete_branches = self.branches.values().filter(|branch| branch.is_complete()).count();
+        summary
+    }
+
+    /// Formats a disassembly of `memory` below `end`, with each line marked `+` if it was executed or `-` if it decodes
+    /// as an instruction that never ran. Branches note which directions they took and data cells note how they were used.
+    pub fn annotate(&self, memory: &Memory, end: usize) -> String {
+        let mut annotated = String::new();
+        for line in self.listing(memory, end) {
+            let marker = match (line.is_instruction, self.was_executed(line.addr)) {
+                (_, true) => '+',
+                (true, false) => '-',
+                (false, false) => ' ',
+            };
+            let note = match self.branch(line.addr) {
+                Some(branch) => format!("  ; {}", branch),
+                None => match (self.was_read(line.addr), self.was_written(line.addr)) {
+                    _ if line.is_instruction => String::new(),
+                    (true, true) => "  ; read, written".to_string(),
+                    (true, false) => "  ; read".to_string(),
+                    (false, true) => "  ; written".to_string(),
+                    (false, false) => String::new(),
+                },
+            };
+            annotated.push_str(&format!("{} {}{}\n", marker, line, note));
+        }
+        annotated
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CoverageSummary {
+    /// Instructions found by the coverage-guided sweep, executed or not.
+    pub instructions: usize,
+    pub executed: usize,
+    /// Conditional jumps executed at least once.
+    pub branches: usize,
+    /// Conditional jumps that went both ways.
+    pub complete_branches: usize,
+}
+
+impl fmt::Display for CoverageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} instructions executed, {} of {} branches taken both ways",
+            self.executed, self.instructions, self.complete_branches, self.branches,
+        )
+    }
+}
+
+impl Program {
+    /// Starts recording coverage, discarding any recorded so far.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::default());
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+    use crate::day21;
+
+    #[test]
+    fn coverage_marks_code_data_and_branches() {
+        let image = [
+            3, 15,          // IN [15]
+            1005, 15, 8,    // JNZ [15], 8
+            104, 0,         // OUT 0
+            99,
+            104, 1,         // OUT 1
+            99,
+            1101, 1, 1, 16, // ADD 1, 1, [16]: never reached
+            0,
+        ];
+        let mut prog = Program::from(&image[..]);
+        prog.enable_coverage();
+        assert_eq!(prog.run_with_inputs(&[0]), vec![0]);
+        {
+            let coverage = prog.coverage().unwrap();
+            assert!(coverage.was_executed(5) && !coverage.was_executed(8) && !coverage.was_executed(6));
+            assert!(coverage.was_read(15) && coverage.was_written(15) && !coverage.was_read(14));
+            assert_eq!(coverage.branch(2), Some(BranchCoverage { taken: false, not_taken: true }));
+
+            let annotated = coverage.annotate(prog.memory(), image.len());
+            let lines: Vec<&str> = annotated.lines().collect();
+            assert_eq!(lines.len(), 8);
+            assert!(lines[1].starts_with("+      2: 1005,15,8") && lines[1].ends_with("JNZ [15], 8  ; never taken"));
+            assert!(lines[4].starts_with("-      8: 104,1") && lines[4].ends_with("OUT 1"));
+            assert!(lines[6].starts_with("-     11:") && lines[6].ends_with("ADD 1, 1, [16]"));
+            assert!(lines[7].starts_with("      15: 0") && lines[7].ends_with("DATA 0  ; read, written"));
+            assert_eq!(
+                coverage.summary(prog.memory(), image.len()),
+                CoverageSummary { instructions: 7, executed: 4, branches: 1, complete_branches: 0 }
+            );
+        }
+
+        // Coverage accumulates across resets.
+        prog.reset();
+        assert_eq!(prog.run_with_inputs(&[1]), vec![1]);
+        let coverage = prog.coverage().unwrap();
+        assert!(coverage.branch(2).unwrap().is_complete());
+        assert_eq!(
+            coverage.summary(prog.memory(), image.len()),
+            CoverageSummary { instructions: 7, executed: 6, branches: 1, complete_branches: 1 }
+        );
+
+        let mut other = Program::from(&image[..]);
+        other.enable_coverage();
+        other.run_with_inputs(&[0]);
+        let mut merged = other.coverage().unwrap().clone();
+        merged.merge(coverage);
+        assert!(merged.was_executed(8) && merged.branch(2).unwrap().is_complete());
+
+        // OUT [rb-1] with rb=0 reads a wrapped address without allocating coverage up to it.
+        let mut prog = Program::from(&[204, -1, 99][..]);
+        prog.enable_coverage();
+        assert_eq!(prog.run_with_inputs(&[]), vec![0]);
+        assert!(prog.coverage().unwrap().was_read(usize::MAX));
+    }
+
+    #[test]
+    fn coverage_day21_springscript() {
+        let input = load_program("inputs/2019/day21.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.enable_coverage();
+        let script: Vec<i64> = day21::SPRINGSCRIPT.iter().map(|&byte| byte as i64).collect();
+        // The current script walks the robot into a hole, so the run ends with the ASCII rendering of its fall.
+        let outputs = prog.run_with_inputs(&script);
+        assert!(prog.is_halted() && outputs.iter().all(|&value| value < 128));
+
+        let coverage = prog.coverage().unwrap();
+        let summary = coverage.summary(prog.memory(), input.len());
+        assert!(summary.executed > 0 && summary.executed < summary.instructions);
+        assert!(summary.complete_branches < summary.branches);
+        assert!(coverage.annotate(prog.memory(), input.len()).lines().any(|line| line.starts_with('-')));
+    }
+}
@@ -0,0 +1,342 @@
+//! Crash dumps written when a program faults, and the post-mortem report built from them.
+//!
+//! A dump is a text file:
+//!
+//! ```text
+//! # Intcode crash dump
+//! fault PC=12 executed illegal instruction 42
+//! pc 12
+//! rb 0
+//! history 0 4 8
+//! in 5
+//! out 3
+//! memory 1101,2,3,...
+//! ```
+//!
+//! `history` lists the PCs of the most recent instructions, oldest first, and the `in`/`out` lines are the most recent I/O.
+
+use super::Program;
+use super::disasm;
+use super::memory::Memory;
+use super::session::{Session, SessionEvent};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many instructions and I/O events a dump keeps when `--crash-dumps` is given.
+pub const DEFAULT_HISTORY: usize = 64;
+
+static DUMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Where programs created on this thread write crash dumps, while inside `with_crash_dumps`.
+    static SCOPED_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Clears the scoped crash dump directory when `with_crash_dumps` returns or unwinds.
+struct ScopeGuard(Option<PathBuf>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPED_DIR.with(|dir| *dir.borrow_mut() = self.0.take());
+    }
+}
+
+/// Runs `f` with crash dumps written into `dir` for every program it creates on this thread, such as a day's programs
+/// when `--crash-dumps` is given. Programs created before or outside `f`, or on other threads, are unaffected.
+pub fn with_crash_dumps<T, F: FnOnce() -> T>(dir: Option<&Path>, f: F) -> T {
+    let previous = SCOPED_DIR.with(|scoped_dir| scoped_dir.replace(dir.map(Path::to_path_buf)));
+    let _guard = ScopeGuard(previous);
+    f()
+}
+
+/// The recorder for a new program: one writing into the directory set by `with_crash_dumps`, if any.
+pub(super) fn scoped_recorder() -> Option<CrashRecorder> {
+    SCOPED_DIR.with(|dir| dir.borrow().clone()).map(|dir| CrashRecorder::new(dir, DEFAULT_HISTORY))
+}
+
+/// Remembers recent execution so a dump can be written if the program faults.
+#[derive(Debug, Clone)]
+pub struct CrashRecorder {
+    dir: PathBuf,
+    capacity: usize,
+    history: VecDeque<usize>,
+    io: VecDeque<SessionEvent>,
+    last_dump: Option<PathBuf>,
+}
+
+impl CrashRecorder {
+    fn new(dir: PathBuf, capacity: usize) -> CrashRecorder {
+        CrashRecorder { dir, capacity, history: VecDeque::new(), io: VecDeque::new(), last_dump: None }
+    }
+
+    pub(super) fn record_step(&mut self, pc: usize) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(pc);
+    }
+
+    pub(super) fn record_io(&mut self, event: SessionEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.io.len() == self.capacity {
+            self.io.pop_front();
+        }
+        self.io.push_back(event);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.history.clear();
+        self.io.clear();
+    }
+
+    /// Writes a dump of `prog`, reporting on stderr where it went. Failing to write is reported but otherwise ignored,
+    /// since the program has already stopped.
+    pub(super) fn write_dump(&mut self, prog: &Program) {
+        let dump = CrashDump {
+            fault: prog.fault().map(|fault| fault.to_string()).unwrap_or_default(),
+            pc: prog.pc,
+            relative_base: prog.relative_base,
+            history: self.history.iter().copied().collect(),
+            io: self.io.iter().copied().collect(),
+            memory: prog.memory.to_vec(),
+        };
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or(0);
+        let path = self.dir.join(format!("intcode-crash-{}-{}.dump", millis, DUMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        match std::fs::create_dir_all(&self.dir).and_then(|_| dump.save(&path)) {
+            Ok(()) => {
+                eprintln!("Intcode fault: {}; crash dump written to {}", dump.fault, path.display());
+                self.last_dump = Some(path);
+            },
+            Err(err) => eprintln!("Intcode fault: {}; failed to write crash dump to {}: {}", dump.fault, path.display(), err),
+        }
+    }
+}
+
+/// The state of a program at the moment it faulted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashDump {
+    pub fault: String,
+    pub pc: usize,
+    pub relative_base: usize,
+    /// PCs of the most recently executed instructions, oldest first. The last one is the faulting instruction.
+    pub history: Vec<usize>,
+    pub io: Vec<SessionEvent>,
+    pub memory: Vec<i64>,
+}
+
+impl CrashDump {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<CrashDump, Box<dyn std::error::Error>> {
+        Ok(std::fs::read_to_string(path)?.parse()?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Formats the post-mortem: the fault, the instruction history, a disassembly from the faulting instruction
+    /// onwards and the recent I/O.
+    pub fn report(&self, context: usize) -> String {
+        let memory = Memory::from(&self.memory[..]);
+        let mut report = format!("Fault: {}\npc={} rb={}, {} memory cells\n", self.fault, self.pc, self.relative_base, self.memory.len());
+
+        report.push_str(&format!("\nLast {} instructions:\n", self.history.len()));
+        for &pc in &self.history {
+            report.push_str(&format!("  {}\n", disasm::disassemble_one(&memory, pc)));
+        }
+
+        report.push_str("\nAround the fault:\n");
+        for (line_idx, line) in disasm::disassemble(&memory, self.pc, context).iter().enumerate() {
+            report.push_str(&format!("{} {}\n", if line_idx == 0 { '>' } else { ' ' }, line));
+        }
+
+        report.push_str(&format!("\nLast {} I/O events:\n", self.io.len()));
+        for event in &self.io {
+            report.push_str(&format!("  {}\n", event));
+        }
+        report
+    }
+}
+
+impl fmt::Display for CrashDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Intcode crash dump")?;
+        writeln!(f, "fault {}", self.fault)?;
+        writeln!(f, "pc {}", self.pc)?;
+        writeln!(f, "rb {}", self.relative_base)?;
+        let history: Vec<String> = self.history.iter().map(usize::to_string).collect();
+        writeln!(f, "history {}", history.join(" "))?;
+        for event in &self.io {
+            writeln!(f, "{}", event)?;
+        }
+        let memory: Vec<String> = self.memory.iter().map(i64::to_string).collect();
+        writeln!(f, "memory {}", memory.join(","))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DumpParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid crash dump on line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for DumpParseError {}
+
+impl FromStr for CrashDump {
+    type Err = DumpParseError;
+
+    fn from_str(s: &str) -> Result<CrashDump, DumpParseError> {
+        let mut dump = CrashDump { fault: String::new(), pc: 0, relative_base: 0, history: Vec::new(), io: Vec::new(), memory: Vec::new() };
+        let mut seen_memory = false;
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| DumpParseError { line: line_idx + 1, message };
+            let (key, value) = match line.find(' ') {
+                Some(space_idx) => (&line[..space_idx], line[space_idx + 1..].trim()),
+                None => (line, ""),
+            };
+            let parse_num = |num_str: &str| num_str.parse::<usize>().map_err(|_| error(format!("invalid number {:?}", num_str)));
+            match key {
+                "fault" => dump.fault = value.to_string(),
+                "pc" => dump.pc = parse_num(value)?,
+                "rb" => dump.relative_base = parse_num(value)?,
+                "history" => dump.history = value.split_whitespace().map(parse_num).collect::<Result<_, _>>()?,
+                "in" | "out" => {
+                    let event = line.parse::<Session>().map_err(|err| error(err.to_string()))?;
+                    dump.io.extend_from_slice(event.events());
+                },
+                "memory" => {
+                    dump.memory = value
+                        .split(',')
+                        .filter(|num_str| !num_str.is_empty())
+                        .map(|num_str| num_str.parse::<i64>().map_err(|_| error(format!("invalid memory value {:?}", num_str))))
+                        .collect::<Result<_, _>>()?;
+                    seen_memory = true;
+                },
+                _ => return Err(error(format!("unknown field {:?}", key))),
+            }
+        }
+        if !seen_memory {
+            return Err(DumpParseError { line: s.lines().count(), message: "missing memory".to_string() });
+        }
+        Ok(dump)
+    }
+}
+
+impl Program {
+    /// Writes a crash dump into `dir` whenever the program faults, keeping the last `history` instructions and I/O events.
+    pub fn enable_crash_dumps<P: Into<PathBuf>>(&mut self, dir: P, history: usize) {
+        self.crash_recorder = Some(CrashRecorder::new(dir.into(), history));
+    }
+
+    /// The path of the most recent crash dump this program wrote.
+    #[allow(dead_code)]
+    pub fn last_crash_dump(&self) -> Option<&Path> {
+        self.crash_recorder.as_ref().and_then(|recorder| recorder.last_dump.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::protect::Fault;
+
+    #[test]
+    fn crashdump_on_illegal_instruction() {
+        let dump_dir = std::env::temp_dir().join(format!("aoc2019-crashdump-{}", std::process::id()));
+        // IN [10]; OUT [10]; then an illegal opcode.
+        let mut prog = Program::from(&[3, 10, 4, 10, 42, 99, 0, 0, 0, 0, 0][..]);
+        prog.catch_faults();
+        prog.enable_crash_dumps(&dump_dir, 2);
+        assert_eq!(prog.run_with_inputs(&[7]), vec![7]);
+        assert_eq!(prog.fault(), Some(Fault::IllegalInstruction { pc: 4, instruction: 42 }));
+        assert!(prog.is_halted());
+
+        let dump = CrashDump::load(prog.last_crash_dump().unwrap()).unwrap();
+        assert_eq!(dump.fault, "PC=4 executed illegal instruction 42");
+        assert_eq!((dump.pc, dump.relative_base), (4, 0));
+        assert_eq!(dump.history, vec![2, 4]);
+        assert_eq!(dump.io, vec![SessionEvent::Input(7), SessionEvent::Output(7)]);
+        assert_eq!(dump.memory[10], 7);
+        assert_eq!(dump.to_string().parse::<CrashDump>().unwrap(), dump);
+
+        let report = dump.report(2);
+        assert!(report.starts_with("Fault: PC=4 executed illegal instruction 42\n"));
+        assert!(report.contains("\n  ") && report.contains("OUT [10]"));
+        assert!(report.lines().any(|line| line.starts_with(">      4: 42") && line.ends_with("DATA 42")));
+        assert!(report.ends_with("Last 2 I/O events:\n  in 7\n  out 7\n"));
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+
+        // Immediate-mode writes are illegal too, and without crash dumps the program simply halts with the fault.
+        let mut prog = Program::from(&[11101, 1, 1, 0, 99][..]);
+        prog.catch_faults();
+        prog.run_with_inputs(&[]);
+        assert_eq!(prog.fault(), Some(Fault::IllegalInstruction { pc: 0, instruction: 11101 }));
+        assert_eq!(prog.last_crash_dump(), None);
+
+        // With no history the dump still has the fault and memory.
+        let mut prog = Program::from(&[3, 10, 4, 10, 42, 99, 0, 0, 0, 0, 0][..]);
+        prog.catch_faults();
+        prog.enable_crash_dumps(&dump_dir, 0);
+        prog.run_with_inputs(&[7]);
+        let dump = CrashDump::load(prog.last_crash_dump().unwrap()).unwrap();
+        assert!(dump.history.is_empty() && dump.io.is_empty());
+        assert_eq!(dump.memory[10], 7);
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+
+        // A jump to a negative address faults at the very end of the address space, where the disassembly stops.
+        let mut prog = Program::from(&[1105, 1, -1][..]);
+        prog.catch_faults();
+        prog.enable_crash_dumps(&dump_dir, 4);
+        prog.run_with_inputs(&[]);
+        let dump = CrashDump::load(prog.last_crash_dump().unwrap()).unwrap();
+        assert_eq!((dump.pc, dump.history.clone()), (usize::MAX, vec![0, usize::MAX]));
+        let report = dump.report(10);
+        let around_fault: Vec<&str> = report.lines().skip_while(|line| *line != "Around the fault:").skip(1).take_while(|line| !line.is_empty()).collect();
+        assert_eq!(around_fault, vec![format!("> {}: {:<28} DATA 0", usize::MAX, 0)]);
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+
+        assert_eq!("pc 4\n".parse::<CrashDump>().unwrap_err().message, "missing memory");
+        assert_eq!("pc x\nmemory 1".parse::<CrashDump>().unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn crashdump_scoped_to_day_runs() {
+        let dump_dir = std::env::temp_dir().join(format!("aoc2019-scoped-crashdump-{}", std::process::id()));
+        let image = [3, 10, 4, 10, 42, 99, 0, 0, 0, 0, 0];
+        // Without `catch_faults` a fault panics, after the dump is written.
+        let result = std::panic::catch_unwind(|| {
+            with_crash_dumps(Some(&dump_dir), || Program::from(&image[..]).run_with_inputs(&[7]))
+        });
+        let message = result.unwrap_err().downcast::<String>().unwrap();
+        assert_eq!(*message, "Intcode fault: PC=4 executed illegal instruction 42");
+        assert_eq!(std::fs::read_dir(&dump_dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+
+        // The scope ends even when it unwinds.
+        assert!(Program::from(&image[..]).crash_recorder.is_none());
+        let mut prog = with_crash_dumps(None, || Program::from(&image[..]));
+        assert!(prog.crash_recorder.is_none());
+        prog.catch_faults();
+        assert_eq!(prog.run_with_inputs(&[7]), vec![7]);
+        assert_eq!(prog.fault(), Some(Fault::IllegalInstruction { pc: 4, instruction: 42 }));
+    }
+}
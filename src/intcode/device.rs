@@ -0,0 +1,203 @@
+//! Decoders for the output protocols of Intcode devices.
+//!
+//! A `Framer` groups a program's outputs into fixed-size frames and decodes each one into a typed event as soon as
+//! it's complete. Frames that don't match the protocol are returned as `FrameError`s.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// A complete frame that doesn't decode. `frame_idx` counts frames from the start of the output.
+    Invalid { frame_idx: usize, values: Vec<i64>, reason: String },
+    /// Output ended part-way through a frame.
+    Incomplete { values: Vec<i64> },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Invalid { frame_idx, values, reason } => write!(f, "Invalid frame {} {:?}: {}", frame_idx, values, reason),
+            FrameError::Incomplete { values } => write!(f, "Output ended with an incomplete frame {:?}", values),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// An event decoded from a fixed-size frame of output values.
+pub trait OutputEvent: Sized {
+    /// The number of output values in each frame.
+    const FRAME_LEN: usize;
+
+    /// Decodes one frame. `frame_idx` lets protocols that alternate between frame types tell them apart.
+    fn decode(values: &[i64], frame_idx: usize) -> Result<Self, String>;
+}
+
+/// Collects output values into frames of `E::FRAME_LEN` and decodes them.
+#[derive(Debug, Clone)]
+pub struct Framer<E> {
+    values: Vec<i64>,
+    frame_idx: usize,
+    event: PhantomData<E>,
+}
+
+impl<E: OutputEvent> Framer<E> {
+    pub fn new() -> Framer<E> {
+        Framer { values: Vec::with_capacity(E::FRAME_LEN), frame_idx: 0, event: PhantomData }
+    }
+
+    /// Adds one output value, returning the decoded event if it completed a frame.
+    pub fn push(&mut self, value: i64) -> Option<Result<E, FrameError>> {
+        self.values.push(value);
+        if self.values.len() < E::FRAME_LEN {
+            return None;
+        }
+        let frame_idx = self.frame_idx;
+        self.frame_idx += 1;
+        let event = E::decode(&self.values, frame_idx)
+            .map_err(|reason| FrameError::Invalid { frame_idx, values: self.values.clone(), reason });
+        self.values.clear();
+        Some(event)
+    }
+
+    /// Checks that the output didn't stop part-way through a frame.
+    pub fn finish(&self) -> Result<(), FrameError> {
+        if self.values.is_empty() {
+            Ok(())
+        } else {
+            Err(FrameError::Incomplete { values: self.values.clone() })
+        }
+    }
+}
+
+impl<E: OutputEvent> Default for Framer<E> {
+    fn default() -> Framer<E> {
+        Framer::new()
+    }
+}
+
+/// Decodes a complete output stream.
+#[allow(dead_code)]
+pub fn decode_all<E: OutputEvent>(values: &[i64]) -> Result<Vec<E>, FrameError> {
+    let mut framer = Framer::new();
+    let mut events = Vec::with_capacity(values.len() / E::FRAME_LEN);
+    for &value in values {
+        if let Some(event) = framer.push(value) {
+            events.push(event?);
+        }
+    }
+    framer.finish()?;
+    Ok(events)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArcadeTile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+/// The day 13 arcade cabinet's output: `x, y, tile` triples, where `-1, 0, score` updates the score display instead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArcadeEvent {
+    Tile { x: i64, y: i64, tile: ArcadeTile },
+    Score(i64),
+}
+
+impl OutputEvent for ArcadeEvent {
+    const FRAME_LEN: usize = 3;
+
+    fn decode(values: &[i64], _frame_idx: usize) -> Result<ArcadeEvent, String> {
+        let (x, y) = (values[0], values[1]);
+        if (x, y) == (-1, 0) {
+            return Ok(ArcadeEvent::Score(values[2]));
+        }
+        if x < 0 || y < 0 {
+            return Err(format!("tile position ({}, {}) is off screen", x, y));
+        }
+        let tile = match values[2] {
+            0 => ArcadeTile::Empty,
+            1 => ArcadeTile::Wall,
+            2 => ArcadeTile::Block,
+            3 => ArcadeTile::Paddle,
+            4 => ArcadeTile::Ball,
+            tile_id => return Err(format!("unknown tile id {}", tile_id)),
+        };
+        Ok(ArcadeEvent::Tile { x, y, tile })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    White,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+/// The day 11 hull painting robot's output, which alternates between the colour to paint and the direction to turn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RobotEvent {
+    Paint(Color),
+    Turn(Turn),
+}
+
+impl OutputEvent for RobotEvent {
+    const FRAME_LEN: usize = 1;
+
+    fn decode(values: &[i64], frame_idx: usize) -> Result<RobotEvent, String> {
+        match (frame_idx % 2, values[0]) {
+            (0, 0) => Ok(RobotEvent::Paint(Color::Black)),
+            (0, 1) => Ok(RobotEvent::Paint(Color::White)),
+            (0, color) => Err(format!("unknown paint colour {}", color)),
+            (_, 0) => Ok(RobotEvent::Turn(Turn::Left)),
+            (_, 1) => Ok(RobotEvent::Turn(Turn::Right)),
+            (_, turn) => Err(format!("unknown turn direction {}", turn)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_decodes_arcade_frames() {
+        assert_eq!(
+            decode_all::<ArcadeEvent>(&[1, 2, 3, -1, 0, 12345, 4, 0, 4]),
+            Ok(vec![
+                ArcadeEvent::Tile { x: 1, y: 2, tile: ArcadeTile::Paddle },
+                ArcadeEvent::Score(12345),
+                ArcadeEvent::Tile { x: 4, y: 0, tile: ArcadeTile::Ball },
+            ])
+        );
+        assert_eq!(
+            decode_all::<ArcadeEvent>(&[1, 2, 3, 5, 6, 9]),
+            Err(FrameError::Invalid { frame_idx: 1, values: vec![5, 6, 9], reason: "unknown tile id 9".to_string() })
+        );
+        assert!(matches!(decode_all::<ArcadeEvent>(&[-1, 1, 0]), Err(FrameError::Invalid { frame_idx: 0, .. })));
+        assert_eq!(decode_all::<ArcadeEvent>(&[1, 2, 3, 4]), Err(FrameError::Incomplete { values: vec![4] }));
+    }
+
+    #[test]
+    fn device_decodes_robot_frames() {
+        let mut framer = Framer::new();
+        assert_eq!(framer.push(1), Some(Ok(RobotEvent::Paint(Color::White))));
+        assert_eq!(framer.push(0), Some(Ok(RobotEvent::Turn(Turn::Left))));
+        assert_eq!(framer.push(0), Some(Ok(RobotEvent::Paint(Color::Black))));
+        assert_eq!(framer.push(1), Some(Ok(RobotEvent::Turn(Turn::Right))));
+        assert!(framer.finish().is_ok());
+
+        assert_eq!(
+            decode_all::<RobotEvent>(&[1, 2]).unwrap_err().to_string(),
+            "Invalid frame 1 [2]: unknown turn direction 2"
+        );
+    }
+}
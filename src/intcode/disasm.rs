@@ -0,0 +1,140 @@
+use super::{Instruction, Op, Param, ParamMode};
+use super::memory::Memory;
+use std::fmt;
+
+impl Op {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Op::Add => "ADD",
+            Op::Multiply => "MUL",
+            Op::Input => "IN",
+            Op::Output => "OUT",
+            Op::JumpIfTrue => "JNZ",
+            Op::JumpIfFalse => "JZ",
+            Op::LessThan => "LT",
+            Op::Equals => "EQ",
+            Op::RelativeBase => "ARB",
+            Op::Halt => "HLT",
+        }
+    }
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        self.opcode.mnemonic()
+    }
+
+    /// The number of parameters the instruction takes.
+    pub fn param_count(&self) -> usize {
+        self.length - 1
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            ParamMode::Position => write!(f, "[{}]", self.value),
+            ParamMode::Immediate => write!(f, "{}", self.value),
+            ParamMode::Relative if self.value < 0 => write!(f, "[rb-{}]", -(self.value as i128)),
+            ParamMode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+/// Formats as assembly, e.g. `ADD [4], 3, [rb+1]`. Positional parameters are in brackets and immediates are bare.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        for (param_idx, param) in self.params[..self.param_count()].iter().enumerate() {
+            write!(f, "{}{}", if param_idx == 0 { " " } else { ", " }, param)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line of a disassembly listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisasmLine {
+    pub addr: usize,
+    /// The raw words covered by this line.
+    pub words: Vec<i64>,
+    pub text: String,
+    /// Whether the words decoded as an instruction rather than being shown as data.
+    pub is_instruction: bool,
+}
+
+impl fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words: Vec<String> = self.words.iter().map(i64::to_string).collect();
+        write!(f, "{:>6}: {:<28} {}", self.addr, words.join(","), self.text)
+    }
+}
+
+/// Disassembles the instruction at `addr`, falling back to a one-word `DATA` line if it doesn't decode. Words past the end
+/// of the address space are left out.
+pub fn disassemble_one(memory: &Memory, addr: usize) -> DisasmLine {
+    match Instruction::decode_at(memory, addr) {
+        Some(ins) => DisasmLine {
+            addr,
+            words: (addr..=usize::MAX).take(ins.length).map(|word_addr| memory.get(word_addr)).collect(),
+            text: ins.to_string(),
+            is_instruction: true,
+        },
+        None => DisasmLine {
+            addr,
+            words: vec![memory.get(addr)],
+            text: format!("DATA {}", memory.get(addr)),
+            is_instruction: false,
+        },
+    }
+}
+
+/// Disassembles `count` consecutive lines with a linear sweep starting at `addr`, stopping early at the end of the
+/// address space.
+pub fn disassemble(memory: &Memory, addr: usize, count: usize) -> Vec<DisasmLine> {
+    let mut lines = Vec::with_capacity(count);
+    let mut addr = addr;
+    while lines.len() < count {
+        let line = disassemble_one(memory, addr);
+        let next_addr = addr.checked_add(line.words.len());
+        lines.push(line);
+        match next_addr {
+            Some(next_addr) => addr = next_addr,
+            None => break,
+        }
+    }
+    lines
+}
+
+/// Disassembles every address below `end` with a linear sweep from zero.
+pub fn disassemble_range(memory: &Memory, end: usize) -> Vec<DisasmLine> {
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < end {
+        let line = disassemble_one(memory, addr);
+        addr += line.words.len();
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disasm_formats_instructions() {
+        let memory = Memory::from(&[1002, 4, 3, 4, 21101, -1, 7, -3, 203, 2, 99, 12345][..]);
+        let lines = disassemble_range(&memory, memory.len());
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["MUL [4], 3, [4]", "ADD -1, 7, [rb-3]", "IN [rb+2]", "HLT", "DATA 12345"]);
+        assert_eq!(lines[1].addr, 4);
+        assert_eq!(lines[1].words, vec![21101, -1, 7, -3]);
+        assert!(!lines[4].is_instruction);
+
+        // Immediate-mode writes and unknown modes don't decode.
+        let memory = Memory::from(&[11101, 1, 1, 1, 301, 0][..]);
+        assert!(Instruction::decode_at(&memory, 0).is_none());
+        assert!(Instruction::decode_at(&memory, 4).is_none());
+    }
+}
@@ -0,0 +1,184 @@
+//! Scripted I/O expectations for tests: declare the conversation a program should have and check it step by step.
+
+use super::prelude::*;
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Expect an input request and answer it with this value.
+    Input(i64),
+    Output(i64),
+    Halt,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Input(value) => write!(f, "input (to be given {})", value),
+            Step::Output(value) => write!(f, "output {}", value),
+            Step::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+/// An expected conversation, built up with `input`, `output` and `halt`.
+///
+/// If the conversation doesn't end with `halt`, the program is left paused at its next input request once every step
+/// has been matched, so it can be checked again with a further conversation. An output after the last step has already
+/// happened by the time it's seen, so it's a mismatch rather than a pause.
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    steps: Vec<Step>,
+}
+
+impl Conversation {
+    pub fn new() -> Conversation {
+        Conversation::default()
+    }
+
+    pub fn input(mut self, value: i64) -> Conversation {
+        self.steps.push(Step::Input(value));
+        self
+    }
+
+    pub fn output(mut self, value: i64) -> Conversation {
+        self.steps.push(Step::Output(value));
+        self
+    }
+
+    pub fn halt(mut self) -> Conversation {
+        self.steps.push(Step::Halt);
+        self
+    }
+
+    /// Runs `prog` against the conversation, describing the first mismatch. Steps are numbered from 1.
+    pub fn check(&self, prog: &mut Program) -> Result<(), String> {
+        let mut step_idx = 0;
+        let mut mismatch = None;
+        prog.run_until_stopped(|io_op, machine| {
+            let expected = self.steps.get(step_idx).copied();
+            let matched = match (io_op, expected) {
+                (IOOperation::Input, None) => return IOReturn::Output(ExecuteAction::Break),
+                (IOOperation::Output(value), None) => {
+                    mismatch = Some(format!("unexpected output {} after the last step, at PC={}", value, machine.pc()));
+                    return IOReturn::Output(ExecuteAction::Break);
+                },
+                (IOOperation::Input, Some(Step::Input(value))) => IOReturn::Input(value),
+                (IOOperation::Output(value), Some(Step::Output(expected_value))) if value == expected_value => {
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+                (_, Some(expected)) => {
+                    let actual = match io_op {
+                        IOOperation::Input => "requested input".to_string(),
+                        IOOperation::Output(value) => format!("output {}", value),
+                    };
+                    mismatch = Some(format!("step {}: expected {}, but the program {} at PC={}", step_idx + 1, expected, actual, machine.pc()));
+                    return IOReturn::Output(ExecuteAction::Break);
+                },
+            };
+            step_idx += 1;
+            matched
+        });
+
+        if let Some(mismatch) = mismatch {
+            return Err(mismatch);
+        }
+        match self.steps.get(step_idx) {
+            None => Ok(()),
+            Some(Step::Halt) if prog.fault().is_none() => Ok(()),
+            Some(expected) => match prog.fault() {
+                Some(fault) => Err(format!("step {}: expected {}, but the program faulted: {}", step_idx + 1, expected, fault)),
+                None => Err(format!("step {}: expected {}, but the program halted at PC={}", step_idx + 1, expected, prog.pc())),
+            },
+        }
+    }
+
+    /// Like `check`, but panics with the mismatch. For use in tests.
+    pub fn assert(&self, prog: &mut Program) {
+        if let Err(mismatch) = self.check(prog) {
+            panic!("Intcode conversation mismatch: {}", mismatch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day7;
+    use crate::day19;
+
+    #[test]
+    fn expect_conversation_mismatches() {
+        // IN [20]; IN [21]; ADD [20], [21] -> [22]; OUT [22]; HLT
+        let image = [3, 20, 3, 21, 1, 20, 21, 22, 4, 22, 99, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        Conversation::new().input(2).input(3).output(5).halt().assert(&mut Program::from(&image[..]));
+
+        let check = |conversation: Conversation| conversation.check(&mut Program::from(&image[..])).unwrap_err();
+        assert_eq!(check(Conversation::new().output(5)), "step 1: expected output 5, but the program requested input at PC=0");
+        assert_eq!(
+            check(Conversation::new().input(2).input(3).output(6)),
+            "step 3: expected output 6, but the program output 5 at PC=8"
+        );
+        assert_eq!(
+            check(Conversation::new().input(2).input(3).halt()),
+            "step 3: expected halt, but the program output 5 at PC=8"
+        );
+        assert_eq!(
+            check(Conversation::new().input(2).input(3).output(5).output(1)),
+            "step 4: expected output 1, but the program halted at PC=10"
+        );
+
+        // A conversation without a halt leaves the program paused, ready for the next one.
+        let mut prog = Program::from(&image[..]);
+        Conversation::new().input(2).assert(&mut prog);
+        assert_eq!(prog.pc(), 2);
+        Conversation::new().input(3).output(5).halt().assert(&mut prog);
+        assert_eq!(
+            check(Conversation::new().input(2).input(3)),
+            "unexpected output 5 after the last step, at PC=8"
+        );
+
+        let mut prog = Program::from(&[3, 5, 42, 99, 0, 0][..]);
+        assert_eq!(
+            Conversation::new().input(1).halt().check(&mut prog).unwrap_err(),
+            "step 2: expected halt, but the program faulted: PC=2 executed illegal instruction 42"
+        );
+    }
+
+    #[test]
+    fn expect_day7_amplifier() {
+        // IN [30] (phase); loop: IN [31]; ADD [30], [31] -> [32]; OUT [32]; JNZ [31], loop; HLT
+        let mut image = vec![3, 30, 3, 31, 1, 30, 31, 32, 4, 32, 1005, 31, 2, 99];
+        image.resize(33, 0);
+        Conversation::new()
+            .input(5).input(1).output(6)
+            .input(2).output(7)
+            .input(0).output(5)
+            .halt()
+            .assert(&mut Program::from(&image[..]));
+
+        let mut prog = Program::from(&image[..]);
+        let amp_result = day7::run_amplifier(&mut prog, 5, 1);
+        assert_eq!((amp_result.output, amp_result.halted), (6, false));
+        let amp_result = day7::run_amplifier(&mut prog, None, 2);
+        assert_eq!((amp_result.output, amp_result.halted), (7, false));
+        let amp_result = day7::run_amplifier(&mut prog, None, 0);
+        assert_eq!((amp_result.output, amp_result.halted), (5, false));
+        assert!(day7::run_amplifier(&mut prog, None, 0).halted);
+    }
+
+    #[test]
+    fn expect_day19_tractor_beam() {
+        // IN [20] (x); IN [21] (y); LT [20], [21] -> [22]; OUT [22]; HLT. The beam covers x < y.
+        let image = [3, 20, 3, 21, 7, 20, 21, 22, 4, 22, 99, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        Conversation::new().input(1).input(2).output(1).halt().assert(&mut Program::from(&image[..]));
+        Conversation::new().input(2).input(1).output(0).halt().assert(&mut Program::from(&image[..]));
+
+        let prog = Program::from(&image[..]);
+        assert!(day19::in_tractor_beam(&prog, 1, 2));
+        assert!(!day19::in_tractor_beam(&prog, 2, 1));
+        assert!(!day19::in_tractor_beam(&prog, 3, 3));
+        // The base program is forked for each query, so it's never run itself.
+        assert_eq!(prog.pc(), 0);
+    }
+}
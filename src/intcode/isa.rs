@@ -0,0 +1,132 @@
+use super::Program;
+use std::fmt;
+use std::str::FromStr;
+
+/// The instruction set as it grew over the puzzles. Running under an earlier profile rejects anything added later.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Isa {
+    /// Add, Multiply and Halt in position mode only.
+    Day2,
+    /// Adds Input, Output, jumps, comparisons and immediate mode.
+    Day5,
+    /// Adds the relative base instruction and relative mode; the complete Intcode computer.
+    #[default]
+    Full,
+}
+
+impl Isa {
+    /// Checks an instruction word against the profile, returning a reason if it isn't allowed.
+    pub fn check(self, word: i64) -> Result<(), String> {
+        if word < 0 {
+            return Err(format!("negative instruction {}", word));
+        }
+        let opcode = word % 100;
+        let max_opcode = match self {
+            Isa::Day2 => 2,
+            Isa::Day5 => 8,
+            Isa::Full => 9,
+        };
+        if !(1..=max_opcode).contains(&opcode) && opcode != 99 {
+            return Err(format!("opcode {} is not in the {} instruction set", opcode, self));
+        }
+
+        let max_mode = match self {
+            Isa::Day2 => 0,
+            Isa::Day5 => 1,
+            Isa::Full => 2,
+        };
+        let mut modes = word / 100;
+        while modes > 0 {
+            if modes % 10 > max_mode {
+                return Err(format!("parameter mode {} is not in the {} instruction set", modes % 10, self));
+            }
+            modes /= 10;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Isa {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Isa::Day2 => "day2",
+            Isa::Day5 => "day5",
+            Isa::Full => "day9",
+        })
+    }
+}
+
+impl FromStr for Isa {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Isa, String> {
+        match name {
+            "day2" => Ok(Isa::Day2),
+            "day5" => Ok(Isa::Day5),
+            "day9" | "full" => Ok(Isa::Full),
+            _ => Err(format!("unknown instruction set `{}` (expected day2, day5, day9 or full)", name)),
+        }
+    }
+}
+
+impl Program {
+    /// Restricts the program to `isa`. Executing an instruction outside it halts the program with `Fault::Unsupported`.
+    pub fn set_isa(&mut self, isa: Isa) {
+        self.isa = isa;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+    use crate::intcode::protect::Fault;
+
+    #[test]
+    fn isa_profiles() {
+        assert!(Isa::Day2.check(1).is_ok() && Isa::Day2.check(2).is_ok() && Isa::Day2.check(99).is_ok());
+        assert_eq!(Isa::Day2.check(1101), Err("parameter mode 1 is not in the day2 instruction set".to_string()));
+        assert_eq!(Isa::Day2.check(3), Err("opcode 3 is not in the day2 instruction set".to_string()));
+        assert!(Isa::Day5.check(1108).is_ok() && Isa::Day5.check(9).is_err() && Isa::Day5.check(204).is_err());
+        assert!(Isa::Full.check(21101).is_ok() && Isa::Full.check(10).is_err() && Isa::Full.check(301).is_err());
+        assert_eq!("day5".parse(), Ok(Isa::Day5));
+        assert_eq!("full".parse(), Ok(Isa::Full));
+        assert!("day7".parse::<Isa>().is_err());
+
+        // Each day's program fits its own profile but not the one before it.
+        for &(path, isa, inputs, earlier_isa) in &[
+            ("inputs/2019/day5.txt", Isa::Day5, &[5][..], Isa::Day2),
+            ("inputs/2019/day9.txt", Isa::Full, &[1][..], Isa::Day5),
+        ] {
+            let input = load_program(path).unwrap();
+            let mut prog = Program::from(&input[..]);
+            prog.catch_faults();
+            prog.set_isa(isa);
+            prog.run_with_inputs(inputs);
+            assert!(prog.is_halted() && prog.fault().is_none());
+
+            prog.reset();
+            prog.set_isa(earlier_isa);
+            prog.run_with_inputs(inputs);
+            assert!(matches!(prog.fault(), Some(Fault::Unsupported { isa, .. }) if isa == earlier_isa));
+        }
+
+        let input = load_program("inputs/2019/day2.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.catch_faults();
+        prog.set_isa(Isa::Day2);
+        prog.run_with_inputs(&[]);
+        assert!(prog.is_halted() && prog.fault().is_none());
+
+        let mut prog = Program::from(&[1101, 1, 2, 0, 99][..]);
+
+        prog.catch_faults();
+        prog.set_isa(Isa::Day2);
+        prog.run_with_inputs(&[]);
+        assert_eq!(prog.fault(), Some(Fault::Unsupported { pc: 0, instruction: 1101, isa: Isa::Day2 }));
+        assert_eq!(
+            prog.fault().unwrap().to_string(),
+            "PC=0 executed unsupported instruction 1101: parameter mode 1 is not in the day2 instruction set"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use super::Program;
+use std::collections::VecDeque;
+
+/// The state needed to undo a single executed instruction.
+#[derive(Debug, Copy, Clone)]
+struct JournalEntry {
+    pc: usize,
+    relative_base: usize,
+    memory_len: usize,
+    /// Address and previous value of the cell the instruction overwrote, if any.
+    overwritten: Option<(usize, i64)>,
+}
+
+/// A bounded history of executed instructions, used to step a `Program` backwards.
+///
+/// Only machine state is journaled. Stepping back over an `Input` or `Output` instruction does not un-consume the value on the I/O side.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    entries: VecDeque<JournalEntry>,
+    capacity: usize,
+    /// The oldest entry, if `begin` evicted it to make room, kept until the instruction is known to have run.
+    evicted: Option<JournalEntry>,
+}
+
+impl Journal {
+    pub(super) fn new(capacity: usize) -> Journal {
+        Journal {
+            entries: VecDeque::with_capacity(capacity.min(1 << 16)),
+            capacity,
+            evicted: None,
+        }
+    }
+
+    pub(super) fn begin(&mut self, pc: usize, relative_base: usize, memory_len: usize) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.evicted = if self.entries.len() == self.capacity { self.entries.pop_front() } else { None };
+        self.entries.push_back(JournalEntry { pc, relative_base, memory_len, overwritten: None });
+    }
+
+    pub(super) fn record_write(&mut self, addr: usize, old_value: i64) {
+        if let Some(entry) = self.entries.back_mut() {
+            entry.overwritten = Some((addr, old_value));
+        }
+    }
+
+    /// Forgets the entry `begin` just added, for an instruction that didn't run after all, restoring any entry it evicted.
+    pub(super) fn discard_last(&mut self) {
+        self.entries.pop_back();
+        if let Some(evicted) = self.evicted.take() {
+            self.entries.push_front(evicted);
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.evicted = None;
+    }
+
+    /// The address written by the most recently journaled instruction, if it wrote to memory.
+    pub fn last_write(&self) -> Option<usize> {
+        self.entries.back().and_then(|entry| entry.overwritten).map(|(addr, _)| addr)
+    }
+
+    /// The PCs of the journaled instructions, oldest first.
+    pub fn pcs(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entries.iter().map(|entry| entry.pc)
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Program {
+    /// Starts journaling executed instructions, keeping at most `capacity` of them for `step_back`. Any existing history is discarded.
+    pub fn enable_journal(&mut self, capacity: usize) {
+        self.journal = Some(Journal::new(capacity));
+    }
+
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Undoes the most recently executed instruction. Returns `false` if there is no journaled history left.
+    #[allow(dead_code)]
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self.journal.as_mut().and_then(|journal| journal.entries.pop_back()) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        if let Some((addr, old_value)) = entry.overwritten {
+            self.memory.set(addr, old_value);
+        }
+        // Undo any memory growth caused by the instruction's write.
+        self.memory.truncate(entry.memory_len);
+        self.pc = entry.pc;
+        self.relative_base = entry.relative_base;
+        self.halted = false;
+        self.fault = None;
+        true
+    }
+
+    /// Steps backwards until the PC equals `pc`, always undoing at least one instruction.
+    ///
+    /// Returns `false` if the history ran out first, in which case the program is left at the oldest journaled state.
+    #[allow(dead_code)]
+    pub fn run_back_to(&mut self, pc: usize) -> bool {
+        while self.step_back() {
+            if self.pc == pc {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::prelude::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn journal_step_back_to_start() {
+        let input = load_program("inputs/2019/day9.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        let initial = prog.memory().clone();
+        prog.enable_journal(usize::MAX);
+        let mut output = 0;
+        prog.run(|io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(1),
+                IOOperation::Output(value) => {
+                    output = value;
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+            }
+        });
+        assert_eq!(output, 3235019597);
+        assert!(prog.is_halted());
+        assert!(prog.memory().len() > initial.len());
+
+        assert!(prog.step_back());
+        assert!(!prog.is_halted());
+        assert!(prog.run_back_to(0));
+        assert!(prog.journal().unwrap().is_empty());
+        assert_eq!(prog.pc(), 0);
+        assert_eq!(prog.relative_base(), 0);
+        assert_eq!(prog.memory(), &initial);
+    }
+
+    #[test]
+    fn journal_is_bounded() {
+        let input = load_program("inputs/2019/day5.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.enable_journal(5);
+        prog.run(|io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(5),
+                IOOperation::Output(_) => IOReturn::Output(ExecuteAction::Continue),
+            }
+        });
+        assert_eq!(prog.journal().unwrap().len(), 5);
+        assert!(!prog.run_back_to(0));
+        assert!(prog.journal().unwrap().is_empty());
+        assert!(!prog.step_back());
+
+        // Stepping forward again from the rewound state reaches the same halt.
+        let mut output = 0;
+        prog.run(|io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(5),
+                IOOperation::Output(value) => {
+                    output = value;
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+            }
+        });
+        assert!(prog.is_halted());
+        assert_eq!(output, 16694270);
+    }
+
+    #[test]
+    fn journal_keeps_history_when_input_pauses() {
+        // OUT 1; OUT 2; IN [10]; HLT. Pausing at the input mustn't lose the oldest entry of a full journal.
+        let mut prog = Program::from(&[104, 1, 104, 2, 3, 10, 99, 0, 0, 0, 0][..]);
+        prog.enable_journal(2);
+        assert_eq!(prog.run_with_inputs(&[]), vec![1, 2]);
+        assert_eq!(prog.journal().unwrap().pcs().collect::<Vec<_>>(), vec![0, 2]);
+        assert!(prog.step_back() && prog.step_back());
+        assert_eq!(prog.pc(), 0);
+
+        // A fork of a journaled program is journaled too, with no history of its own.
+        prog.run_with_inputs(&[]);
+        let mut fork = prog.fork();
+        assert!(fork.journal().unwrap().is_empty());
+        assert_eq!(fork.journal().unwrap().capacity(), 2);
+        fork.run_with_inputs(&[7]);
+        assert_eq!(fork.journal().unwrap().pcs().collect::<Vec<_>>(), vec![4, 6]);
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::path::Path;
+
+/// Magic bytes at the start of a binary Intcode image.
+pub const IMAGE_MAGIC: &[u8; 4] = b"ICB\x01";
+
+/// Parses an Intcode program from text.
+///
+/// Values may be separated by commas, whitespace or both, and `#` starts a comment that runs to the end of the line.
+pub fn parse_program(text: &str) -> Result<Vec<i64>, ProgramParseError> {
+    let mut prog = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+
+        let mut token_start = None;
+        // Chain a trailing separator so the last token on the line gets flushed.
+        for (byte_idx, ch) in line.char_indices().chain(std::iter::once((line.len(), ','))) {
+            let is_separator = ch == ',' || ch.is_whitespace();
+            match (token_start, is_separator) {
+                (None, false) => token_start = Some(byte_idx),
+                (Some(start), true) => {
+                    let token = &line[start..byte_idx];
+                    match token.parse::<i64>() {
+                        Ok(value) => prog.push(value),
+                        Err(_) => return Err(ProgramParseError::InvalidToken {
+                            line: line_idx + 1,
+                            column: line[..start].chars().count() + 1,
+                            token: token.to_string(),
+                        }),
+                    }
+                    token_start = None;
+                },
+                _ => (),
+            }
+        }
+    }
+    Ok(prog)
+}
+
+/// Decodes a binary Intcode image.
+///
+/// The image is `IMAGE_MAGIC` followed by each value as a zigzag-encoded little-endian base 128 varint.
+pub fn decode_image(bytes: &[u8]) -> Result<Vec<i64>, ProgramParseError> {
+    if !bytes.starts_with(IMAGE_MAGIC) {
+        return Err(ProgramParseError::BadMagic);
+    }
+
+    let mut prog = Vec::new();
+    let mut encoded = 0_u64;
+    let mut shift = 0;
+    for (offset, &byte) in bytes.iter().enumerate().skip(IMAGE_MAGIC.len()) {
+        if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+            return Err(ProgramParseError::Overflow { offset });
+        }
+        encoded |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            prog.push((encoded >> 1) as i64 ^ -((encoded & 1) as i64));
+            encoded = 0;
+            shift = 0;
+        }
+    }
+
+    if shift != 0 {
+        return Err(ProgramParseError::Truncated);
+    }
+    Ok(prog)
+}
+
+/// Encodes a program into the binary image format read by `decode_image`.
+#[allow(dead_code)]
+pub fn encode_image(prog: &[i64]) -> Vec<u8> {
+    let mut bytes = IMAGE_MAGIC.to_vec();
+    for &value in prog {
+        let mut encoded = ((value << 1) ^ (value >> 63)) as u64;
+        while encoded >= 0x80 {
+            bytes.push((encoded as u8 & 0x7f) | 0x80);
+            encoded >>= 7;
+        }
+        bytes.push(encoded as u8);
+    }
+    bytes
+}
+
+/// Loads a program from disk, reading it as a binary image if it starts with `IMAGE_MAGIC` and as text otherwise.
+pub fn load_program<P: AsRef<Path>>(path: P) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.starts_with(IMAGE_MAGIC) {
+        Ok(decode_image(&bytes)?)
+    }
+    else {
+        Ok(parse_program(std::str::from_utf8(&bytes)?)?)
+    }
+}
+
+/// Input serializer for the Intcode days.
+pub fn intcode_gen(input: String) -> Vec<i64> {
+    match parse_program(&input) {
+        Ok(prog) => prog,
+        Err(err) => panic!("Failed to parse Intcode program: {}", err),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramParseError {
+    InvalidToken { line: usize, column: usize, token: String },
+    BadMagic,
+    Overflow { offset: usize },
+    Truncated,
+}
+
+impl fmt::Display for ProgramParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgramParseError::InvalidToken { line, column, token } =>
+                write!(f, "Invalid value {:?} at line {}, column {}", token, line, column),
+            ProgramParseError::BadMagic => write!(f, "Binary image does not start with the Intcode image magic"),
+            ProgramParseError::Overflow { offset } => write!(f, "Value at byte offset {} does not fit in 64 bits", offset),
+            ProgramParseError::Truncated => write!(f, "Binary image ends in the middle of a value"),
+        }
+    }
+}
+
+impl std::error::Error for ProgramParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loader_text_formats() {
+        assert_eq!(parse_program("1,9,10,3,\n2,3,11,0\n99\n"), Ok(vec![1, 9, 10, 3, 2, 3, 11, 0, 99]));
+        assert_eq!(parse_program("# header\n1 2  3 # add\n\t4, -5\n"), Ok(vec![1, 2, 3, 4, -5]));
+        assert_eq!(
+            parse_program("1,2,3\n4,x5,6\n"),
+            Err(ProgramParseError::InvalidToken { line: 2, column: 3, token: String::from("x5") })
+        );
+    }
+
+    #[test]
+    fn loader_binary_image() {
+        let prog = vec![0, 1, -1, 63, -64, 64, 1_000_000_007, i64::MAX, i64::MIN];
+        let image = encode_image(&prog);
+        assert!(image.starts_with(IMAGE_MAGIC));
+        assert_eq!(decode_image(&image), Ok(prog));
+        assert_eq!(decode_image(b"1,2,3"), Err(ProgramParseError::BadMagic));
+        assert_eq!(decode_image(b"ICB\x01\x80"), Err(ProgramParseError::Truncated));
+
+        let input = load_program("inputs/2019/day9.txt").unwrap();
+        assert_eq!(decode_image(&encode_image(&input)), Ok(input));
+    }
+}
@@ -0,0 +1,93 @@
+use super::Program;
+use super::memory::PAGE_SIZE;
+use std::fmt;
+
+/// A memory cell whose value differs between two snapshots.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CellDiff {
+    pub addr: usize,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl fmt::Display for CellDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {} -> {}", self.addr, self.before, self.after)
+    }
+}
+
+/// Compares two memory snapshots cell by cell. The shorter snapshot is treated as zero-extended, matching how `Program` grows memory.
+pub fn diff_memory(before: &[i64], after: &[i64]) -> Vec<CellDiff> {
+    let len = before.len().max(after.len());
+    (0..len)
+        .filter_map(|addr| {
+            let before = before.get(addr).copied().unwrap_or(0);
+            let after = after.get(addr).copied().unwrap_or(0);
+            if before != after {
+                Some(CellDiff { addr, before, after })
+            }
+            else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Runs two fresh copies of `image` with different inputs and compares their final memory.
+pub fn diff_runs(image: &[i64], inputs_before: &[i64], inputs_after: &[i64]) -> Vec<CellDiff> {
+    let mut prog_before = Program::from(image);
+    prog_before.run_with_inputs(inputs_before);
+    let mut prog_after = Program::from(image);
+    prog_after.run_with_inputs(inputs_after);
+    diff_memory(&prog_before.memory().to_vec(), &prog_after.memory().to_vec())
+}
+
+impl Program {
+    /// Returns every memory cell whose current value differs from the program's original image.
+    ///
+    /// Pages the program never wrote to are still shared with the image, so only touched pages are compared.
+    pub fn diff_from_default(&self) -> Vec<CellDiff> {
+        let page_count = self.image.page_count().max(self.memory.page_count());
+        (0..page_count)
+            .filter(|&page_idx| !self.memory.shares_page(&self.image, page_idx))
+            .flat_map(|page_idx| page_idx * PAGE_SIZE..(page_idx + 1) * PAGE_SIZE)
+            .filter_map(|addr| {
+                let before = self.image.get(addr);
+                let after = self.memory.get(addr);
+                if before != after {
+                    Some(CellDiff { addr, before, after })
+                }
+                else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn memdiff_from_default() {
+        let input = load_program("inputs/2019/day2.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.memory_mut().set(1, 12);
+        prog.memory_mut().set(2, 2);
+        prog.run_with_inputs(&[]);
+        let diffs = prog.diff_from_default();
+        assert_eq!(diffs[0], CellDiff { addr: 0, before: input[0], after: 6327510 });
+        assert_eq!(diffs[1], CellDiff { addr: 1, before: input[1], after: 12 });
+        assert_eq!(diffs[2], CellDiff { addr: 2, before: input[2], after: 2 });
+    }
+
+    #[test]
+    fn memdiff_between_runs() {
+        let input = load_program("inputs/2019/day5.txt").unwrap();
+        assert!(diff_runs(&input, &[5], &[5]).is_empty());
+        let diffs = diff_runs(&input, &[1], &[5]);
+        assert!(diffs.iter().any(|diff| diff.before == 16434972 && diff.after == 16694270));
+    }
+}
@@ -0,0 +1,195 @@
+use std::ops::Index;
+use std::sync::Arc;
+
+const PAGE_BITS: usize = 10;
+pub const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const PAGE_MASK: usize = PAGE_SIZE - 1;
+/// Writes must be below this address. Anything higher is almost certainly a negative or wrapped address, and growing
+/// the page table to reach it would exhaust memory.
+pub const ADDRESS_LIMIT: usize = 1 << 30;
+
+type Page = [i64; PAGE_SIZE];
+
+/// Paged, copy-on-write Intcode memory.
+///
+/// Pages are reference counted, so cloning a `Memory` only copies page pointers. A page is copied the first time a clone writes to it.
+/// Pages that have never been written are not allocated at all and read as zero.
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    pages: Vec<Option<Arc<Page>>>,
+    len: usize,
+}
+
+impl Memory {
+    /// Returns the value at `addr`. Addresses past the end of memory read as zero.
+    #[inline]
+    pub fn get(&self, addr: usize) -> i64 {
+        match self.pages.get(addr >> PAGE_BITS) {
+            Some(Some(page)) => page[addr & PAGE_MASK],
+            _ => 0,
+        }
+    }
+
+    /// Stores `value` at `addr`, growing memory if needed and copying the page first if it is shared. Panics if `addr` is
+    /// at or past `ADDRESS_LIMIT`.
+    #[inline]
+    pub fn set(&mut self, addr: usize, value: i64) {
+        assert!(addr < ADDRESS_LIMIT, "Intcode write to address {} is out of range", addr);
+        let page_idx = addr >> PAGE_BITS;
+        if page_idx >= self.pages.len() {
+            self.pages.resize(page_idx + 1, None);
+        }
+        if addr >= self.len {
+            self.len = addr + 1;
+        }
+        let page = self.pages[page_idx].get_or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        Arc::make_mut(page)[addr & PAGE_MASK] = value;
+    }
+
+    /// The number of addressable cells, i.e. one past the highest address that was ever part of the image or written to.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Shrinks memory to `len` cells, zeroing anything written past it.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let page_count = (len + PAGE_MASK) >> PAGE_BITS;
+        self.pages.truncate(page_count);
+        if let Some(Some(page)) = self.pages.last_mut() {
+            let tail_start = len & PAGE_MASK;
+            if tail_start != 0 && page[tail_start..].iter().any(|&value| value != 0) {
+                for value in &mut Arc::make_mut(page)[tail_start..] {
+                    *value = 0;
+                }
+            }
+        }
+        self.len = len;
+    }
+
+    /// Returns whether page `page_idx` is the same allocation in both memories, meaning neither has written to it since they were cloned.
+    pub fn shares_page(&self, other: &Memory, page_idx: usize) -> bool {
+        match (self.pages.get(page_idx).and_then(Option::as_ref), other.pages.get(page_idx).and_then(Option::as_ref)) {
+            (Some(page), Some(other_page)) => Arc::ptr_eq(page, other_page),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        (0..self.len).map(move |addr| self.get(addr))
+    }
+
+    pub fn to_vec(&self) -> Vec<i64> {
+        self.iter().collect()
+    }
+}
+
+impl From<&[i64]> for Memory {
+    fn from(values: &[i64]) -> Memory {
+        let pages = values
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Some(Arc::new(page))
+            })
+            .collect();
+        Memory { pages, len: values.len() }
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+
+    fn index(&self, addr: usize) -> &i64 {
+        match self.pages.get(addr >> PAGE_BITS) {
+            Some(Some(page)) => &page[addr & PAGE_MASK],
+            _ => &0,
+        }
+    }
+}
+
+impl PartialEq for Memory {
+    fn eq(&self, other: &Memory) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Memory {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::prelude::*;
+    use crate::intcode::loader::load_program;
+    use crate::intcode::protect::Fault;
+
+    #[test]
+    fn fork_shares_untouched_pages() {
+        let input = load_program("inputs/2019/day9.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        let mut fork = prog.fork();
+        for page_idx in 0..prog.memory().page_count() {
+            assert!(fork.memory().shares_page(prog.memory(), page_idx));
+        }
+
+        fork.memory_mut().set(0, 99);
+        assert!(!fork.memory().shares_page(prog.memory(), 0));
+        assert_eq!(prog.memory()[0], input[0]);
+        assert!(fork.run_with_inputs(&[]).is_empty());
+        assert!(fork.is_halted());
+
+        // A machine forked mid-run continues exactly like the original.
+        prog.step(|_| IOReturn::Input(1));
+        let mut fork = prog.fork();
+        assert_eq!(fork.pc(), prog.pc());
+        assert_eq!(fork.run_with_inputs(&[1]), vec![3235019597]);
+        assert_eq!(prog.run_with_inputs(&[1]), vec![3235019597]);
+        assert_eq!(fork.memory(), prog.memory());
+    }
+
+    #[test]
+    fn memory_grows_on_write_only() {
+        let mut prog = Program::from(&[4, 1000, 1101, 7, 8, 2000, 99][..]);
+        assert_eq!(prog.run_with_inputs(&[]), vec![0]);
+        assert_eq!(prog.memory().len(), 2001);
+        assert_eq!(prog.memory()[2000], 15);
+        assert_eq!(prog.memory()[1000], 0);
+        prog.memory_mut().truncate(7);
+        assert_eq!(prog.memory()[2000], 0);
+        prog.reset();
+        assert_eq!(prog.memory().to_vec(), vec![4, 1000, 1101, 7, 8, 2000, 99]);
+    }
+
+    #[test]
+    fn memory_rejects_out_of_range_writes() {
+        // ADD 1, 1, [-1] would write to usize::MAX.
+        let mut prog = Program::from(&[1101, 1, 1, -1, 99][..]);
+        prog.catch_faults();
+        prog.run_with_inputs(&[]);
+        assert_eq!(prog.fault(), Some(Fault::AddressOutOfRange { pc: 0, addr: usize::MAX }));
+        assert_eq!(prog.fault().unwrap().to_string(), format!("PC=0 wrote to out-of-range address {}", usize::MAX));
+        assert_eq!(prog.memory().len(), 5);
+
+        // So would input through a wrapped relative address.
+        let mut prog = Program::from(&[203, -1, 99][..]);
+        prog.catch_faults();
+        assert_eq!(prog.run_with_inputs(&[5]), vec![]);
+        assert_eq!(prog.fault(), Some(Fault::AddressOutOfRange { pc: 0, addr: usize::MAX }));
+
+        let result = std::panic::catch_unwind(|| Program::from(&[99][..]).memory_mut().set(ADDRESS_LIMIT, 1));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,406 @@
+//! Peephole optimisation of Intcode images.
+//!
+//! Intcode carries no relocation information, so the optimiser never moves code. Instructions are rewritten in place,
+//! and instructions that do nothing are bypassed by a jump written over the start of their run. Jumps that land on such
+//! a run are retargeted past it, removing it from those paths entirely.
+//!
+//! Only code reachable from address 0 through static control flow is rewritten. An instruction is left alone if any
+//! of its words might be a jump target other than its start, if the program reads or writes it as data through a
+//! position-mode parameter, or if it overlaps another reachable instruction. Computed jumps and relative-mode accesses
+//! can't be fully analysed, so `verify` replays recorded sessions to confirm the result behaves identically.
+
+use super::{Instruction, Op, ParamMode, Program};
+use super::memory::Memory;
+use super::session::{Divergence, Session};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct OptimiseReport {
+    /// Arithmetic and comparisons on two immediates replaced by a constant move.
+    pub constants_folded: usize,
+    /// Moves such as `MUL x, 1` rewritten to the canonical `ADD x, 0`.
+    pub moves_simplified: usize,
+    /// Runs of instructions with no effect (jumps to the next instruction and self-moves) replaced by a single jump past them.
+    pub dead_runs_bypassed: usize,
+    /// Total instructions inside those runs.
+    pub dead_instructions: usize,
+    /// Jumps to the start of a bypassed run retargeted to its end.
+    pub jumps_threaded: usize,
+}
+
+impl fmt::Display for OptimiseReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} constants folded, {} moves simplified, {} dead instructions bypassed in {} runs, {} jumps threaded past them",
+            self.constants_folded, self.moves_simplified, self.dead_instructions, self.dead_runs_bypassed, self.jumps_threaded,
+        )
+    }
+}
+
+/// Static facts about an image used to decide which rewrites are safe.
+struct Analysis {
+    /// Reachable instructions by start address.
+    code: BTreeMap<usize, Instruction>,
+    /// Addresses something might jump to, or hold as a code pointer.
+    potential_targets: HashSet<usize>,
+    /// Addresses accessed as data through position-mode parameters.
+    data_accesses: HashSet<usize>,
+    /// Starts of instructions that overlap another reachable instruction.
+    overlapping: HashSet<usize>,
+}
+
+impl Analysis {
+    fn new(memory: &Memory) -> Analysis {
+        let mut code = BTreeMap::new();
+        let mut potential_targets = HashSet::new();
+        let mut worklist = vec![0];
+        potential_targets.insert(0);
+        while let Some(addr) = worklist.pop() {
+            if code.contains_key(&addr) || addr >= memory.len() {
+                continue;
+            }
+            let ins = match Instruction::decode_at(memory, addr) {
+                Some(ins) => ins,
+                None => continue,
+            };
+            code.insert(addr, ins);
+
+            let (falls_through, target) = match ins.opcode {
+                Op::Halt => (false, None),
+                Op::JumpIfTrue | Op::JumpIfFalse => {
+                    let jumps_on_nonzero = ins.opcode == Op::JumpIfTrue;
+                    let (may_jump, may_fall) = match ins.params[0].mode {
+                        ParamMode::Immediate => {
+                            let jumps = (ins.params[0].value != 0) == jumps_on_nonzero;
+                            (jumps, !jumps)
+                        },
+                        _ => (true, true),
+                    };
+                    let target = match ins.params[1].mode {
+                        ParamMode::Immediate if may_jump => Some(ins.params[1].value),
+                        _ => None,
+                    };
+                    (may_fall, target)
+                },
+                _ => (true, None),
+            };
+            if falls_through {
+                worklist.push(addr + ins.length);
+            }
+            if let Some(target) = target {
+                if target >= 0 {
+                    potential_targets.insert(target as usize);
+                    worklist.push(target as usize);
+                }
+            }
+        }
+
+        // Computed jumps land on addresses the program built from constants, so treat every immediate operand and
+        // every word outside reachable code as a possible target.
+        let mut data_accesses = HashSet::new();
+        let mut covered = HashSet::new();
+        for (&addr, ins) in &code {
+            covered.extend(addr..addr + ins.length);
+            for param in &ins.params[..ins.length - 1] {
+                match param.mode {
+                    ParamMode::Immediate if param.value >= 0 => { potential_targets.insert(param.value as usize); },
+                    ParamMode::Position if param.value >= 0 => { data_accesses.insert(param.value as usize); },
+                    _ => (),
+                }
+            }
+        }
+        for addr in (0..memory.len()).filter(|addr| !covered.contains(addr)) {
+            if memory.get(addr) >= 0 {
+                potential_targets.insert(memory.get(addr) as usize);
+            }
+        }
+
+        let mut overlapping = HashSet::new();
+        let mut prev: Option<(usize, usize)> = None;
+        for (&addr, ins) in &code {
+            if let Some((prev_addr, prev_end)) = prev {
+                if addr < prev_end {
+                    overlapping.insert(prev_addr);
+                    overlapping.insert(addr);
+                }
+            }
+            prev = Some((addr, addr + ins.length));
+        }
+
+        Analysis { code, potential_targets, data_accesses, overlapping }
+    }
+
+    /// Whether the instruction at `addr` may have its words rewritten.
+    fn can_rewrite(&self, addr: usize, length: usize) -> bool {
+        !self.overlapping.contains(&addr)
+            && (addr + 1..addr + length).all(|word_addr| !self.potential_targets.contains(&word_addr))
+            && (addr..addr + length).all(|word_addr| !self.data_accesses.contains(&word_addr))
+    }
+}
+
+fn encode(opcode: i64, params: &[(ParamMode, i64)]) -> Vec<i64> {
+    let mut modes = 0;
+    let mut words = vec![0];
+    for (param_idx, &(mode, value)) in params.iter().enumerate() {
+        let mode_digit = match mode {
+            ParamMode::Position => 0,
+            ParamMode::Immediate => 1,
+            ParamMode::Relative => 2,
+        };
+        modes += mode_digit * 10_i64.pow(param_idx as u32 + 2);
+        words.push(value);
+    }
+    words[0] = opcode + modes;
+    words
+}
+
+fn same_cell(a: (ParamMode, i64), b: (ParamMode, i64)) -> bool {
+    a == b && a.0 != ParamMode::Immediate
+}
+
+enum Rewrite {
+    Folded(Vec<i64>),
+    Move(Vec<i64>),
+}
+
+/// Rewrites arithmetic on constants and disguised moves into the canonical move `ADD x, 0, dest`.
+fn simplify(ins: &Instruction) -> Option<Rewrite> {
+    let param = |idx: usize| (ins.params[idx].mode, ins.params[idx].value);
+    let imm = |value| (ParamMode::Immediate, value);
+    match ins.opcode {
+        Op::Add | Op::Multiply | Op::LessThan | Op::Equals => (),
+        _ => return None,
+    }
+
+    let (lhs, rhs, dest) = (param(0), param(1), param(2));
+    if ins.opcode == Op::Add && rhs == imm(0) {
+        // Already canonical.
+        return None;
+    }
+    if lhs.0 == ParamMode::Immediate && rhs.0 == ParamMode::Immediate {
+        let value = match ins.opcode {
+            Op::Add => lhs.1.wrapping_add(rhs.1),
+            Op::Multiply => lhs.1.wrapping_mul(rhs.1),
+            Op::LessThan => (lhs.1 < rhs.1) as i64,
+            _ => (lhs.1 == rhs.1) as i64,
+        };
+        return Some(Rewrite::Folded(encode(1, &[imm(value), imm(0), dest])));
+    }
+
+    let moved = match ins.opcode {
+        Op::Add if lhs == imm(0) => rhs,
+        Op::Multiply if rhs == imm(1) => lhs,
+        Op::Multiply if lhs == imm(1) => rhs,
+        Op::Multiply if lhs == imm(0) || rhs == imm(0) => imm(0),
+        _ => return None,
+    };
+    Some(Rewrite::Move(encode(1, &[moved, imm(0), dest])))
+}
+
+/// Whether an instruction has no effect: a jump whose every outcome is the next instruction, or a move of a cell onto itself.
+fn is_dead(addr: usize, ins: &Instruction) -> bool {
+    match ins.opcode {
+        Op::JumpIfTrue | Op::JumpIfFalse => {
+            ins.params[1].mode == ParamMode::Immediate && ins.params[1].value == (addr + ins.length) as i64
+        },
+        Op::Add => {
+            ins.params[1].mode == ParamMode::Immediate && ins.params[1].value == 0
+                && same_cell((ins.params[0].mode, ins.params[0].value), (ins.params[2].mode, ins.params[2].value))
+        },
+        _ => false,
+    }
+}
+
+/// Optimises an Intcode image, returning the new image and a summary of what changed.
+#[allow(dead_code)]
+pub fn optimise(image: &[i64]) -> (Vec<i64>, OptimiseReport) {
+    let mut report = OptimiseReport::default();
+    let mut optimised = image.to_vec();
+    let analysis = Analysis::new(&Memory::from(image));
+
+    for (&addr, ins) in &analysis.code {
+        if !analysis.can_rewrite(addr, ins.length) {
+            continue;
+        }
+        let words = match simplify(ins) {
+            Some(Rewrite::Folded(words)) => {
+                report.constants_folded += 1;
+                words
+            },
+            Some(Rewrite::Move(words)) => {
+                report.moves_simplified += 1;
+                words
+            },
+            None => continue,
+        };
+        optimised[addr..addr + words.len()].copy_from_slice(&words);
+    }
+
+    // Bypass runs of consecutive dead instructions. Only the first three words of a run change, so jumps landing
+    // on later instructions in the run still see the original (harmless) code.
+    let memory = Memory::from(&optimised[..]);
+    let mut run: Option<(usize, usize, usize)> = None; // (start, end, instruction count)
+    let mut runs = Vec::new();
+    for &addr in analysis.code.keys() {
+        let ins = match Instruction::decode_at(&memory, addr) {
+            Some(ins) => ins,
+            None => continue,
+        };
+        let dead = is_dead(addr, &ins) && !analysis.overlapping.contains(&addr);
+        run = match (run, dead) {
+            (Some((start, end, count)), true) if end == addr => Some((start, addr + ins.length, count + 1)),
+            (previous, true) => {
+                runs.extend(previous);
+                Some((addr, addr + ins.length, 1))
+            },
+            (previous, false) => {
+                runs.extend(previous);
+                None
+            },
+        };
+    }
+    runs.extend(run);
+
+    let mut bypassed = BTreeMap::new();
+    for (start, end, count) in runs {
+        if !analysis.can_rewrite(start, 3) {
+            continue;
+        }
+        optimised[start..start + 3].copy_from_slice(&[1105, 1, end as i64]);
+        bypassed.insert(start, end);
+        report.dead_runs_bypassed += 1;
+        report.dead_instructions += count;
+    }
+
+    // Thread jumps that land on a bypassed run straight to its end. Dead jumps are left alone: they point at the next
+    // instruction, which is in their own run, and may have just been overwritten by a bypass.
+    for &addr in analysis.code.keys() {
+        let ins = match Instruction::decode_at(&memory, addr) {
+            Some(ins) => ins,
+            None => continue,
+        };
+        let is_jump = ins.opcode == Op::JumpIfTrue || ins.opcode == Op::JumpIfFalse;
+        if !is_jump || is_dead(addr, &ins) || ins.params[1].mode != ParamMode::Immediate || !analysis.can_rewrite(addr, ins.length) {
+            continue;
+        }
+        let target = ins.params[1].value;
+        if let Some(&end) = bypassed.get(&(target as usize)).filter(|_| target >= 0) {
+            optimised[addr + 2] = end as i64;
+            report.jumps_threaded += 1;
+        }
+    }
+
+    (optimised, report)
+}
+
+/// Replays every session against `image`, returning the index of the first session that diverges.
+#[allow(dead_code)]
+pub fn verify(image: &[i64], sessions: &[Session]) -> Result<(), (usize, Divergence)> {
+    let mut prog = Program::from(image);
+    for (session_idx, session) in sessions.iter().enumerate() {
+        prog.reset();
+        session.replay(&mut prog).map_err(|divergence| (session_idx, divergence))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::prelude::*;
+    use crate::intcode::loader::load_program;
+    use crate::intcode::session::SessionEvent;
+
+    fn record_session(image: &[i64], inputs: &[i64]) -> Session {
+        let mut prog = Program::from(image);
+        let mut session = Session::new();
+        let mut input_iter = inputs.iter().copied();
+        prog.run(session.recorder(|io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(input_iter.next().unwrap()),
+                IOOperation::Output(_) => IOReturn::Output(ExecuteAction::Continue),
+            }
+        }));
+        session
+    }
+
+    #[test]
+    fn optimise_rewrites_peepholes() {
+        let image = [
+            3, 100,             // IN [100]
+            1102, 60, 70, 101,  // MUL 60, 70, [101]
+            1002, 100, 1, 102,  // MUL [100], 1, [102]
+            1001, 102, 0, 102,  // ADD [102], 0, [102]
+            1106, 0, 17,        // JZ 0, 17
+            1005, 100, 20,      // JNZ [100], 20
+            1, 101, 102, 103,   // ADD [101], [102], [103]
+            4, 103,             // OUT [103]
+            99,
+        ];
+        let (optimised, report) = optimise(&image);
+        assert_eq!(optimised, vec![
+            3, 100,
+            1101, 4200, 0, 101,
+            1001, 100, 0, 102,
+            1105, 1, 20, 102,
+            1106, 0, 17,
+            1005, 100, 20,
+            1, 101, 102, 103,
+            4, 103,
+            99,
+        ]);
+        assert_eq!(
+            report,
+            OptimiseReport { constants_folded: 1, moves_simplified: 1, dead_runs_bypassed: 1, dead_instructions: 3, jumps_threaded: 0 }
+        );
+
+        let sessions = [record_session(&image, &[5]), record_session(&image, &[0])];
+        assert_eq!(sessions[0].outputs().collect::<Vec<_>>(), vec![4205]);
+        assert_eq!(verify(&optimised, &sessions), Ok(()));
+
+        // A lone jump to the next instruction is bypassed too, and jumps to it go straight past it.
+        let image = [
+            3, 100,             // IN [100]
+            1006, 100, 8,       // JZ [100], 8
+            104, 1,             // OUT 1
+            99,
+            1005, 100, 11,      // JNZ [100], 11
+            4, 100,             // OUT [100]
+            99,
+        ];
+        let (optimised, report) = optimise(&image);
+        assert_eq!(optimised, vec![3, 100, 1006, 100, 11, 104, 1, 99, 1105, 1, 11, 4, 100, 99]);
+        assert_eq!(report, OptimiseReport { dead_runs_bypassed: 1, dead_instructions: 1, jumps_threaded: 1, ..OptimiseReport::default() });
+        let sessions = [record_session(&image, &[5]), record_session(&image, &[0])];
+        assert_eq!(verify(&optimised, &sessions), Ok(()));
+    }
+
+    #[test]
+    fn optimise_leaves_jump_targets_and_data_alone() {
+        let image = [
+            1102, 20, 30, 100,  // MUL 20, 30, [100]
+            1101, 2, 0, 101,    // ADD 2, 0, [101]: keeps a pointer into the middle of the MUL above
+            1102, 4, 5, 9,      // MUL 4, 5, [9]: overwrites its own operand
+            99,
+        ];
+        let (optimised, report) = optimise(&image);
+        assert_eq!(optimised, image.to_vec());
+        assert_eq!(report, OptimiseReport::default());
+    }
+
+    #[test]
+    fn optimise_puzzle_inputs() {
+        for &(path, ref inputs) in &[("inputs/2019/day5.txt", vec![1, 5]), ("inputs/2019/day9.txt", vec![1, 2])] {
+            let image = load_program(path).unwrap();
+            let sessions: Vec<Session> = inputs.iter().map(|&input| record_session(&image, &[input])).collect();
+            let (optimised, _) = optimise(&image);
+            assert_eq!(verify(&optimised, &sessions), Ok(()));
+        }
+
+        let image = load_program("inputs/2019/day5.txt").unwrap();
+        let wrong = Session::from(vec![SessionEvent::Input(5), SessionEvent::Output(0)]);
+        assert!(matches!(verify(&optimise(&image).0, &[record_session(&image, &[1]), wrong]), Err((1, _))));
+    }
+}
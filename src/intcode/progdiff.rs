@@ -0,0 +1,340 @@
+//! Structural comparison of two Intcode programs, such as the same day's input from two accounts.
+//!
+//! Both images are disassembled with a linear sweep and the listings are aligned on each instruction's opcode and
+//! parameter modes, so code that only differs in its constants still lines up. Data tables rarely disassemble the same
+//! way twice, so an unaligned stretch that's the same size in both programs is compared word by word instead.
+
+use super::disasm::{self, DisasmLine};
+use super::memory::Memory;
+use std::fmt;
+
+/// One line of the aligned listings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// Identical words in both programs.
+    Same { left_addr: usize, right_addr: usize, words: Vec<i64>, is_instruction: bool },
+    /// Instructions with the same opcode and parameter modes whose parameters differ.
+    Constants { left: DisasmLine, right: DisasmLine },
+    /// A data word that differs.
+    Data { left_addr: usize, right_addr: usize, left: i64, right: i64 },
+    LeftOnly(DisasmLine),
+    RightOnly(DisasmLine),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    pub identical_instructions: usize,
+    pub identical_data_words: usize,
+    /// Aligned instructions that differ only in their parameters.
+    pub differing_constants: usize,
+    pub differing_data_words: usize,
+    /// Words in unaligned code that only one program has.
+    pub left_only_words: usize,
+    pub right_only_words: usize,
+}
+
+impl DiffSummary {
+    /// Whether both programs have the same code, differing at most in constants and data.
+    pub fn same_structure(&self) -> bool {
+        self.left_only_words == 0 && self.right_only_words == 0
+    }
+}
+
+impl fmt::Display for DiffSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} identical instructions, {} with different constants; {} identical data words, {} different; {} words only in left, {} only in right",
+            self.identical_instructions,
+            self.differing_constants,
+            self.identical_data_words,
+            self.differing_data_words,
+            self.left_only_words,
+            self.right_only_words,
+        )
+    }
+}
+
+/// The aligned listings of two programs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramDiff {
+    pub lines: Vec<DiffLine>,
+}
+
+/// What the alignment matches on: the whole instruction word for instructions, and nothing more for data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Shape {
+    Instruction(i64),
+    Data,
+}
+
+fn shape(line: &DisasmLine) -> Shape {
+    if line.is_instruction {
+        Shape::Instruction(line.words[0])
+    }
+    else {
+        Shape::Data
+    }
+}
+
+/// Aligns two listings on their shapes with a longest common subsequence, returning the matched index pairs in order.
+fn align(left: &[Shape], right: &[Shape]) -> Vec<(usize, usize)> {
+    let prefix = left.iter().zip(right).take_while(|(l, r)| l == r).count();
+    let suffix = left[prefix..].iter().rev().zip(right[prefix..].iter().rev()).take_while(|(l, r)| l == r).count();
+    let (left_mid, right_mid) = (&left[prefix..left.len() - suffix], &right[prefix..right.len() - suffix]);
+
+    // lcs[i][j] is the length of the longest common subsequence of left_mid[i..] and right_mid[j..].
+    let width = right_mid.len() + 1;
+    let mut lcs = vec![0u32; (left_mid.len() + 1) * width];
+    for i in (0..left_mid.len()).rev() {
+        for j in (0..right_mid.len()).rev() {
+            lcs[i * width + j] = if left_mid[i] == right_mid[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            }
+            else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|idx| (idx, idx)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < left_mid.len() && j < right_mid.len() {
+        if left_mid[i] == right_mid[j] {
+            pairs.push((prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        }
+        else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            i += 1;
+        }
+        else {
+            j += 1;
+        }
+    }
+    pairs.extend((0..suffix).map(|idx| (left.len() - suffix + idx, right.len() - suffix + idx)));
+    pairs
+}
+
+fn compare_words(left_addr: usize, right_addr: usize, left: &[i64], right: &[i64], out: &mut Vec<DiffLine>) {
+    for (word_idx, (&left_word, &right_word)) in left.iter().zip(right).enumerate() {
+        let (left_addr, right_addr) = (left_addr + word_idx, right_addr + word_idx);
+        out.push(if left_word == right_word {
+            DiffLine::Same { left_addr, right_addr, words: vec![left_word], is_instruction: false }
+        }
+        else {
+            DiffLine::Data { left_addr, right_addr, left: left_word, right: right_word }
+        });
+    }
+}
+
+/// Adds the lines between two aligned pairs, comparing them as data if both sides cover the same number of words.
+fn push_gap(left: &[DisasmLine], right: &[DisasmLine], out: &mut Vec<DiffLine>) {
+    let left_words: Vec<i64> = left.iter().flat_map(|line| line.words.iter().copied()).collect();
+    let right_words: Vec<i64> = right.iter().flat_map(|line| line.words.iter().copied()).collect();
+    if !left.is_empty() && left_words.len() == right_words.len() {
+        compare_words(left[0].addr, right[0].addr, &left_words, &right_words, out);
+    }
+    else {
+        out.extend(left.iter().cloned().map(DiffLine::LeftOnly));
+        out.extend(right.iter().cloned().map(DiffLine::RightOnly));
+    }
+}
+
+/// Compares two program images.
+pub fn diff_programs(left: &[i64], right: &[i64]) -> ProgramDiff {
+    let left_lines = disasm::disassemble_range(&Memory::from(left), left.len());
+    let right_lines = disasm::disassemble_range(&Memory::from(right), right.len());
+    let left_shapes: Vec<Shape> = left_lines.iter().map(shape).collect();
+    let right_shapes: Vec<Shape> = right_lines.iter().map(shape).collect();
+
+    let mut lines = Vec::new();
+    let (mut next_left, mut next_right) = (0, 0);
+    for (left_idx, right_idx) in align(&left_shapes, &right_shapes) {
+        push_gap(&left_lines[next_left..left_idx], &right_lines[next_right..right_idx], &mut lines);
+        let (left_line, right_line) = (&left_lines[left_idx], &right_lines[right_idx]);
+        if left_line.words == right_line.words {
+            lines.push(DiffLine::Same {
+                left_addr: left_line.addr,
+                right_addr: right_line.addr,
+                words: left_line.words.clone(),
+                is_instruction: left_line.is_instruction,
+            });
+        }
+        else if left_line.is_instruction {
+            lines.push(DiffLine::Constants { left: left_line.clone(), right: right_line.clone() });
+        }
+        else {
+            compare_words(left_line.addr, right_line.addr, &left_line.words, &right_line.words, &mut lines);
+        }
+        next_left = left_idx + 1;
+        next_right = right_idx + 1;
+    }
+    push_gap(&left_lines[next_left..], &right_lines[next_right..], &mut lines);
+    ProgramDiff { lines }
+}
+
+impl ProgramDiff {
+    pub fn summary(&self) -> DiffSummary {
+        let mut summary = DiffSummary::default();
+        for line in &self.lines {
+            match line {
+                DiffLine::Same { is_instruction: true, .. } => summary.identical_instructions += 1,
+                DiffLine::Same { words, .. } => summary.identical_data_words += words.len(),
+                DiffLine::Constants { .. } => summary.differing_constants += 1,
+                DiffLine::Data { .. } => summary.differing_data_words += 1,
+                DiffLine::LeftOnly(line) => summary.left_only_words += line.words.len(),
+                DiffLine::RightOnly(line) => summary.right_only_words += line.words.len(),
+            }
+        }
+        summary
+    }
+}
+
+/// How many differing values of a data table to show before eliding the rest.
+const DATA_PREVIEW: usize = 8;
+
+fn preview(values: &[i64]) -> String {
+    let shown: Vec<String> = values.iter().take(DATA_PREVIEW).map(i64::to_string).collect();
+    if values.len() > DATA_PREVIEW {
+        format!("{},...", shown.join(","))
+    }
+    else {
+        shown.join(",")
+    }
+}
+
+/// Formats the diff as hunks: `=` for identical runs, `~` for instructions whose constants differ (left then right),
+/// `#` for data tables with differing words, and `-`/`+` for code only in the left or right program.
+impl fmt::Display for ProgramDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut line_idx = 0;
+        while line_idx < self.lines.len() {
+            match &self.lines[line_idx] {
+                DiffLine::Same { left_addr, right_addr, .. } => {
+                    let run_len = self.lines[line_idx..].iter().take_while(|line| matches!(line, DiffLine::Same { .. })).count();
+                    let words: usize = self.lines[line_idx..line_idx + run_len]
+                        .iter()
+                        .map(|line| match line {
+                            DiffLine::Same { words, .. } => words.len(),
+                            _ => 0,
+                        })
+                        .sum();
+                    writeln!(f, "= {}..{} | {}..{}: {} lines identical", left_addr, left_addr + words, right_addr, right_addr + words, run_len)?;
+                    line_idx += run_len;
+                },
+                DiffLine::Constants { left, right } => {
+                    writeln!(f, "~ {}", left)?;
+                    writeln!(f, "  {}", right)?;
+                    line_idx += 1;
+                },
+                DiffLine::Data { left_addr, right_addr, .. } => {
+                    // A table runs until the last differing word before the next instruction or unaligned line.
+                    let table_len = self.lines[line_idx..]
+                        .iter()
+                        .take_while(|line| matches!(line, DiffLine::Data { .. } | DiffLine::Same { is_instruction: false, .. }))
+                        .enumerate()
+                        .filter(|(_, line)| matches!(line, DiffLine::Data { .. }))
+                        .last()
+                        .map_or(1, |(table_idx, _)| table_idx + 1);
+                    let (mut left_values, mut right_values) = (Vec::new(), Vec::new());
+                    for line in &self.lines[line_idx..line_idx + table_len] {
+                        if let DiffLine::Data { left, right, .. } = line {
+                            left_values.push(*left);
+                            right_values.push(*right);
+                        }
+                    }
+                    writeln!(
+                        f,
+                        "# {}..{} | {}..{}: {} of {} data words differ: {} -> {}",
+                        left_addr,
+                        left_addr + table_len,
+                        right_addr,
+                        right_addr + table_len,
+                        left_values.len(),
+                        table_len,
+                        preview(&left_values),
+                        preview(&right_values),
+                    )?;
+                    line_idx += table_len;
+                },
+                DiffLine::LeftOnly(line) => {
+                    writeln!(f, "- {}", line)?;
+                    line_idx += 1;
+                },
+                DiffLine::RightOnly(line) => {
+                    writeln!(f, "+ {}", line)?;
+                    line_idx += 1;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn progdiff_aligns_code_constants_and_data() {
+        // IN [12]; ADD [12], 5 -> [13]; OUT [13]; HLT; then a data table.
+        let left = [3, 12, 1001, 12, 5, 13, 4, 13, 99, 42, 50, 60, 0, 0];
+        let diff = diff_programs(&left, &left);
+        assert_eq!(diff.summary(), DiffSummary { identical_instructions: 4, identical_data_words: 5, ..DiffSummary::default() });
+
+        // Different constant and table contents, plus an extra OUT [12] before the halt.
+        let right = [3, 14, 1001, 14, 6, 15, 4, 15, 4, 14, 99, 42, 51, 61, 0, 0];
+        let diff = diff_programs(&left, &right);
+        let summary = diff.summary();
+        assert_eq!(
+            summary,
+            DiffSummary {
+                identical_instructions: 1,
+                identical_data_words: 3,
+                differing_constants: 3,
+                differing_data_words: 2,
+                left_only_words: 0,
+                right_only_words: 2,
+            }
+        );
+        assert!(!summary.same_structure());
+        assert!(diff.lines.contains(&DiffLine::Data { left_addr: 10, right_addr: 12, left: 50, right: 51 }));
+        assert_eq!(
+            diff.to_string().lines().collect::<Vec<_>>(),
+            vec![
+                "~      0: 3,12                         IN [12]",
+                "       0: 3,14                         IN [14]",
+                "~      2: 1001,12,5,13                 ADD [12], 5, [13]",
+                "       2: 1001,14,6,15                 ADD [14], 6, [15]",
+                "~      6: 4,13                         OUT [13]",
+                "       6: 4,15                         OUT [15]",
+                "+      8: 4,14                         OUT [14]",
+                "= 8..10 | 10..12: 2 lines identical",
+                "# 10..12 | 12..14: 2 of 2 data words differ: 50,60 -> 51,61",
+                "= 12..14 | 14..16: 2 lines identical",
+            ]
+        );
+
+        // Unaligned stretches of the same size are compared as data.
+        let diff = diff_programs(&[99, 1, 1, 1, 1], &[99, 2, 2, 2, 2]);
+        assert!(diff.summary().same_structure());
+        assert_eq!(diff.summary().differing_data_words, 4);
+        assert_eq!(diff.to_string(), "= 0..1 | 0..1: 1 lines identical\n# 1..5 | 1..5: 4 of 4 data words differ: 1,1,1,1 -> 2,2,2,2\n");
+    }
+
+    #[test]
+    fn progdiff_puzzle_input() {
+        let image = load_program("inputs/2019/day9.txt").unwrap();
+        assert!(diff_programs(&image, &image).lines.iter().all(|line| matches!(line, DiffLine::Same { .. })));
+
+        // Another account's input would differ in constants like the first instruction's operand.
+        let mut other = image.clone();
+        other[1] += 1;
+        let diff = diff_programs(&image, &other);
+        assert!(diff.summary().same_structure());
+        assert_eq!(diff.summary().differing_constants, 1);
+        assert!(diff.to_string().starts_with("~      0: "));
+    }
+}
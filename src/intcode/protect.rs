@@ -0,0 +1,244 @@
+use super::{Instruction, Op, Program};
+use super::isa::Isa;
+use std::fmt;
+use std::ops::Range;
+
+/// An illegal instruction, or one that violated a protected region or the program's instruction set. The program halts with its PC on
+/// the offending instruction, which hasn't had any effect, so `Program::clear_fault` can retry it. `Program::run` panics
+/// on a fault unless `Program::catch_faults` was called.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The instruction at `pc` tried to write to a read-only address.
+    WriteProtected { pc: usize, addr: usize },
+    /// The instruction at `pc` would jump or fall through to an address that may not be executed. If execution started
+    /// at a no-execute address, `target` is `pc`.
+    NoExecute { pc: usize, target: usize },
+    /// The instruction at `pc` isn't part of the instruction set the program was restricted to.
+    Unsupported { pc: usize, instruction: i64, isa: Isa },
+    /// The word at `pc` isn't a valid instruction: an unknown opcode or parameter mode, or an immediate-mode write.
+    IllegalInstruction { pc: usize, instruction: i64 },
+    /// The instruction at `pc` tried to write at or past `memory::ADDRESS_LIMIT`, usually a negative address.
+    AddressOutOfRange { pc: usize, addr: usize },
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::WriteProtected { pc, addr } => write!(f, "PC={} wrote to read-only address {}", pc, addr),
+            Fault::NoExecute { pc, target } => write!(f, "PC={} transferred control to no-execute address {}", pc, target),
+            Fault::Unsupported { pc, instruction, isa } => {
+                let reason = isa.check(*instruction).err().unwrap_or_default();
+                write!(f, "PC={} executed unsupported instruction {}: {}", pc, instruction, reason)
+            },
+            Fault::IllegalInstruction { pc, instruction } => write!(f, "PC={} executed illegal instruction {}", pc, instruction),
+            Fault::AddressOutOfRange { pc, addr } => write!(f, "PC={} wrote to out-of-range address {}", pc, addr),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+/// Address ranges the program itself may not write to or execute. Writes through `Program::memory_mut` are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct Protection {
+    read_only: Vec<Range<usize>>,
+    no_execute: Vec<Range<usize>>,
+}
+
+impl Protection {
+    pub(super) fn is_read_only(&self, addr: usize) -> bool {
+        self.read_only.iter().any(|range| range.contains(&addr))
+    }
+
+    pub(super) fn is_no_execute(&self, addr: usize) -> bool {
+        self.no_execute.iter().any(|range| range.contains(&addr))
+    }
+}
+
+impl Program {
+    /// Makes `range` read-only for the program's own writes.
+    #[allow(dead_code)]
+    pub fn protect_read_only(&mut self, range: Range<usize>) {
+        self.protection.get_or_insert_with(Protection::default).read_only.push(range);
+    }
+
+    /// Forbids the program from executing instructions starting in `range`.
+    #[allow(dead_code)]
+    pub fn protect_no_execute(&mut self, range: Range<usize>) {
+        self.protection.get_or_insert_with(Protection::default).no_execute.push(range);
+    }
+
+    /// Makes the whole original image read-only, catching any self-modifying code.
+    #[allow(dead_code)]
+    pub fn protect_image(&mut self) {
+        self.protect_read_only(0..self.image.len());
+    }
+
+    #[allow(dead_code)]
+    pub fn clear_protection(&mut self) {
+        self.protection = None;
+    }
+
+    /// The protection fault that halted the program, if any.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    /// Makes `run` leave a fault for the caller to check with `fault()` instead of panicking. Forks inherit this.
+    pub fn catch_faults(&mut self) {
+        self.catch_faults = true;
+    }
+
+    /// Clears the fault and resumes the program, so the next step retries the faulting instruction. Useful after
+    /// changing the protection or memory that caused it.
+    #[allow(dead_code)]
+    pub fn clear_fault(&mut self) {
+        if self.fault.take().is_some() {
+            self.halted = false;
+        }
+    }
+
+    /// Where control goes after `ins` runs at the current PC, worked out without running it, or `None` if it halts.
+    fn next_pc(&self, ins: Instruction) -> Option<usize> {
+        let jump_if = |taken: bool| if taken { self.peek_value(ins.params[1]) as usize } else { self.pc + ins.length };
+        match ins.opcode {
+            Op::JumpIfTrue => Some(jump_if(self.peek_value(ins.params[0]) != 0)),
+            Op::JumpIfFalse => Some(jump_if(self.peek_value(ins.params[0]) == 0)),
+            Op::Halt => None,
+            _ => Some(self.pc + ins.length),
+        }
+    }
+
+    /// Checks `ins`, about to run at the current PC, against the protected regions before any of its effects happen.
+    pub(super) fn check_protection(&self, ins: Instruction) -> Option<Fault> {
+        let protection = self.protection.as_ref()?;
+        let pc = self.pc;
+        if protection.is_no_execute(pc) {
+            return Some(Fault::NoExecute { pc, target: pc });
+        }
+        if let Some(addr) = ins.write_param().and_then(|param| self.param_addr(param)) {
+            if protection.is_read_only(addr) {
+                return Some(Fault::WriteProtected { pc, addr });
+            }
+        }
+        match self.next_pc(ins) {
+            Some(target) if protection.is_no_execute(target) => Some(Fault::NoExecute { pc, target }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::prelude::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn protect_read_only_regions() {
+        // ADD 2, 3, [9]; OUT [9]; HLT, with a data cell at 9.
+        let image = [1101, 2, 3, 9, 4, 9, 99, 0, 0, 0];
+        let mut prog = Program::from(&image[..]);
+        prog.catch_faults();
+        prog.protect_read_only(8..10);
+        assert_eq!(prog.run_with_inputs(&[]), vec![]);
+        assert_eq!(prog.fault(), Some(Fault::WriteProtected { pc: 0, addr: 9 }));
+        assert_eq!(prog.fault().unwrap().to_string(), "PC=0 wrote to read-only address 9");
+        assert!(prog.is_halted());
+        assert_eq!(prog.pc(), 0);
+        assert_eq!(prog.memory()[9], 0);
+
+        // Only the program's own writes are checked.
+        prog.memory_mut().set(9, 1);
+        prog.reset();
+        assert_eq!(prog.fault(), None);
+        prog.clear_protection();
+        assert_eq!(prog.run_with_inputs(&[]), vec![5]);
+
+        // The faulting instruction isn't journaled, so stepping back undoes the one before it.
+        let mut prog = Program::from(&[104, 0, 1101, 2, 3, 11, 4, 11, 99, 0, 0, 0][..]);
+        prog.catch_faults();
+        prog.protect_image();
+        prog.enable_journal(4);
+        assert_eq!(prog.run_with_inputs(&[]), vec![0]);
+        assert_eq!(prog.fault(), Some(Fault::WriteProtected { pc: 2, addr: 11 }));
+        assert!(prog.step_back());
+        assert_eq!((prog.fault(), prog.is_halted(), prog.pc()), (None, false, 0));
+    }
+
+    #[test]
+    fn protect_no_execute_regions() {
+        // JNZ 1, 4 into a no-execute OUT.
+        let mut prog = Program::from(&[1105, 1, 4, 99, 104, 7, 99][..]);
+        prog.catch_faults();
+        prog.protect_no_execute(4..7);
+        assert_eq!(prog.run_with_inputs(&[]), vec![]);
+        assert_eq!(prog.fault(), Some(Fault::NoExecute { pc: 0, target: 4 }));
+        assert_eq!(prog.fault().unwrap().to_string(), "PC=0 transferred control to no-execute address 4");
+
+        // Falling through into a no-execute region faults too, before the preceding instruction runs.
+        let mut prog = Program::from(&[104, 1, 104, 2, 99][..]);
+        prog.catch_faults();
+        prog.protect_no_execute(2..4);
+        assert_eq!(prog.run_with_inputs(&[]), vec![]);
+        assert_eq!(prog.fault(), Some(Fault::NoExecute { pc: 0, target: 2 }));
+
+        // So does starting in one.
+        let mut prog = Program::from(&[104, 1, 99][..]);
+        prog.catch_faults();
+        prog.protect_no_execute(0..1);
+        assert_eq!(prog.run_with_inputs(&[]), vec![]);
+        assert_eq!(prog.fault(), Some(Fault::NoExecute { pc: 0, target: 0 }));
+
+        // Day 9's BOOST program never executes outside its own image.
+        let input = load_program("inputs/2019/day9.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.catch_faults();
+        prog.protect_no_execute(input.len()..usize::MAX);
+        assert_eq!(prog.run_with_inputs(&[1]), vec![3235019597]);
+        assert_eq!(prog.fault(), None);
+    }
+
+    #[test]
+    fn protect_resume_after_fault() {
+        // IN [10]; OUT [10]; HLT, where the input would fall through into a no-execute region.
+        let image = [3, 10, 4, 10, 99, 0, 0, 0, 0, 0, 0];
+        let mut prog = Program::from(&image[..]);
+        prog.catch_faults();
+        prog.protect_no_execute(2..4);
+        assert_eq!(prog.run_with_inputs(&[5]), vec![]);
+        assert_eq!(prog.fault(), Some(Fault::NoExecute { pc: 0, target: 2 }));
+        assert_eq!(prog.memory()[10], 0);
+
+        // The faulting input wasn't consumed, so resuming reads each input exactly once.
+        prog.clear_protection();
+        prog.clear_fault();
+        assert_eq!((prog.fault(), prog.is_halted(), prog.pc()), (None, false, 0));
+        assert_eq!(prog.run_with_inputs(&[6]), vec![6]);
+        assert!(prog.is_halted() && prog.fault().is_none());
+
+        // A write to a read-only address faults before the input is requested.
+        let mut prog = Program::from(&image[..]);
+        prog.catch_faults();
+        prog.protect_read_only(10..11);
+        let mut requests = 0;
+        prog.run(|_| {
+            requests += 1;
+            IOReturn::Input(5)
+        });
+        assert_eq!((prog.fault(), requests), (Some(Fault::WriteProtected { pc: 0, addr: 10 }), 0));
+        prog.clear_protection();
+        prog.clear_fault();
+        assert_eq!(prog.run_with_inputs(&[7]), vec![7]);
+
+        // A jump into a no-execute region resumes by taking the jump once.
+        let mut prog = Program::from(&[104, 1, 1105, 1, 7, 104, 2, 104, 3, 99][..]);
+        prog.catch_faults();
+        prog.protect_no_execute(7..9);
+        assert_eq!(prog.run_with_inputs(&[]), vec![1]);
+        assert_eq!(prog.fault(), Some(Fault::NoExecute { pc: 2, target: 7 }));
+        prog.clear_protection();
+        prog.clear_fault();
+        assert_eq!(prog.run_with_inputs(&[]), vec![3]);
+    }
+}
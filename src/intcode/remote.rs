@@ -0,0 +1,320 @@
+//! A line-based debugging protocol for driving a `Program` over a local TCP socket.
+//!
+//! Each request is one line; each response is zero or more `out <value>` lines for values the program output while
+//! running, followed by a single line starting with `ok` or `err`. Addresses and values are decimal.
+//!
+//! | Request                    | Response                                                     |
+//! |----------------------------|--------------------------------------------------------------|
+//! | `step [count]`             | `ok <state>` after executing up to `count` instructions      |
+//! | `continue [max-steps]`     | `ok <state>` after running until a breakpoint, halt or input |
+//! | `break <addr>`             | `ok` after setting a breakpoint                              |
+//! | `delete <addr>`            | `ok` after clearing a breakpoint                             |
+//! | `breakpoints`              | `ok <addr>...`                                               |
+//! | `read <addr> [count]`      | `ok <value>...`                                              |
+//! | `write <addr> <value>...`  | `ok`                                                         |
+//! | `regs`                     | `ok pc=<pc> rb=<relative base> halted=<0/1>`                 |
+//! | `reg <pc/rb> <value>`      | `ok`                                                         |
+//! | `input <value>...`         | `ok` after queueing values for input instructions            |
+//! | `disasm <addr> [count]`    | `ok <addr>: <instruction>` with further lines joined by `;`  |
+//! | `quit`                     | `ok` and the connection closes                               |
+//!
+//! `<state>` is one of `running`, `break`, `waiting` (an input instruction found the queue empty) or `halted`,
+//! followed by `pc=<pc>`. If the program faults, `step` and `continue` reply with `err` and the fault instead.
+//!
+//! Addresses above `MAX_ADDR`, and `read` or `disasm` counts above `MAX_WORDS`, are rejected with `err`.
+
+use super::{ExecuteAction, IOOperation, IOReturn, Program};
+use super::disasm;
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+/// The port `DebugServer` listens on when none is given.
+pub const DEFAULT_PORT: u16 = 7419;
+/// The highest address a request may use, so a mistyped `write` can't grow memory without bound.
+pub const MAX_ADDR: usize = 1 << 20;
+/// The most words a single `read` or `disasm` may cover.
+pub const MAX_WORDS: usize = 1024;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum StopReason {
+    Running,
+    Breakpoint,
+    Waiting,
+    Halted,
+}
+
+impl StopReason {
+    fn name(self) -> &'static str {
+        match self {
+            StopReason::Running => "running",
+            StopReason::Breakpoint => "break",
+            StopReason::Waiting => "waiting",
+            StopReason::Halted => "halted",
+        }
+    }
+}
+
+/// The debugger state for one connection: breakpoints and queued input.
+struct DebugSession<'a> {
+    prog: &'a mut Program,
+    breakpoints: BTreeSet<usize>,
+    inputs: VecDeque<i64>,
+}
+
+impl<'a> DebugSession<'a> {
+    /// Executes one instruction, sending any output to `out`. A fault, now or from an earlier step, is an error.
+    fn step_one<W: Write>(&mut self, out: &mut W) -> Result<StopReason, String> {
+        if let Some(fault) = self.prog.fault() {
+            return Err(fault.to_string());
+        }
+        if self.prog.halted {
+            return Ok(StopReason::Halted);
+        }
+
+        let inputs = &mut self.inputs;
+        let mut waiting = false;
+        let mut outputs = Vec::new();
+        self.prog.step(|io_op| {
+            match io_op {
+                IOOperation::Input => match inputs.pop_front() {
+                    Some(value) => IOReturn::Input(value),
+                    None => {
+                        waiting = true;
+                        IOReturn::Output(ExecuteAction::Break)
+                    },
+                },
+                IOOperation::Output(value) => {
+                    outputs.push(value);
+                    IOReturn::Output(ExecuteAction::Continue)
+                },
+            }
+        });
+        for value in outputs {
+            writeln!(out, "out {}", value).map_err(|err| err.to_string())?;
+        }
+        if let Some(fault) = self.prog.fault() {
+            return Err(fault.to_string());
+        }
+
+        Ok(if self.prog.halted {
+            StopReason::Halted
+        } else if waiting {
+            StopReason::Waiting
+        } else if self.breakpoints.contains(&self.prog.pc) {
+            StopReason::Breakpoint
+        } else {
+            StopReason::Running
+        })
+    }
+
+    fn run<W: Write>(&mut self, max_steps: Option<usize>, out: &mut W) -> Result<String, String> {
+        let mut reason = StopReason::Running;
+        let mut steps = 0;
+        while max_steps != Some(steps) {
+            reason = self.step_one(out)?;
+            steps += 1;
+            if reason != StopReason::Running {
+                break;
+            }
+        }
+        Ok(format!("{} pc={}", reason.name(), self.prog.pc))
+    }
+
+    /// Handles one request line, writing the response to `out`. Returns `false` once the client has asked to quit.
+    fn handle<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let command = words.first().copied().unwrap_or("");
+        match self.execute(command, &words[words.len().min(1)..], out) {
+            Ok(response) if response.is_empty() => writeln!(out, "ok")?,
+            Ok(response) => writeln!(out, "ok {}", response)?,
+            Err(message) => writeln!(out, "err {}", message)?,
+        }
+        out.flush()?;
+        Ok(command != "quit")
+    }
+
+    fn execute<W: Write>(&mut self, command: &str, args: &[&str], out: &mut W) -> Result<String, String> {
+        let value_arg = |idx: usize| -> Result<i64, String> {
+            let arg = args.get(idx).ok_or_else(|| format!("`{}` needs more arguments", command))?;
+            arg.parse::<i64>().map_err(|_| format!("invalid number `{}`", arg))
+        };
+        let addr_arg = |idx: usize| -> Result<usize, String> {
+            match value_arg(idx)? {
+                value if value >= 0 && value as usize <= MAX_ADDR => Ok(value as usize),
+                value => Err(format!("invalid address {}", value)),
+            }
+        };
+        let count_arg = |idx: usize, default: usize| -> Result<usize, String> {
+            if idx >= args.len() {
+                return Ok(default);
+            }
+            match value_arg(idx)? {
+                value if value >= 0 => Ok(value as usize),
+                value => Err(format!("invalid count {}", value)),
+            }
+        };
+        let words_arg = |idx: usize| -> Result<usize, String> {
+            match count_arg(idx, 1)? {
+                count if count <= MAX_WORDS => Ok(count),
+                count => Err(format!("count {} is over the limit of {}", count, MAX_WORDS)),
+            }
+        };
+
+        match command {
+            "step" => self.run(Some(count_arg(0, 1)?), out),
+            "continue" => {
+                let max_steps = if args.is_empty() { None } else { Some(count_arg(0, 0)?) };
+                self.run(max_steps, out)
+            },
+            "break" => {
+                self.breakpoints.insert(addr_arg(0)?);
+                Ok(String::new())
+            },
+            "delete" => {
+                let addr = addr_arg(0)?;
+                if !self.breakpoints.remove(&addr) {
+                    return Err(format!("no breakpoint at {}", addr));
+                }
+                Ok(String::new())
+            },
+            "breakpoints" => Ok(self.breakpoints.iter().map(usize::to_string).collect::<Vec<_>>().join(" ")),
+            "read" => {
+                let addr = addr_arg(0)?;
+                let count = words_arg(1)?;
+                Ok((addr..addr + count).map(|addr| self.prog.memory.get(addr).to_string()).collect::<Vec<_>>().join(" "))
+            },
+            "write" => {
+                let addr = addr_arg(0)?;
+                let values = (1..args.len().max(2)).map(value_arg).collect::<Result<Vec<_>, _>>()?;
+                for (offset, value) in values.into_iter().enumerate() {
+                    self.prog.memory.set(addr + offset, value);
+                }
+                Ok(String::new())
+            },
+            "regs" => Ok(format!("pc={} rb={} halted={}", self.prog.pc, self.prog.relative_base, self.prog.halted as u8)),
+            "reg" => {
+                let value = addr_arg(1)?;
+                match args[0] {
+                    "pc" => self.prog.pc = value,
+                    "rb" => self.prog.relative_base = value,
+                    name => return Err(format!("unknown register `{}`", name)),
+                }
+                Ok(String::new())
+            },
+            "input" => {
+                let values = (0..args.len()).map(value_arg).collect::<Result<Vec<_>, _>>()?;
+                self.inputs.extend(values);
+                Ok(String::new())
+            },
+            "disasm" => {
+                let lines = disasm::disassemble(&self.prog.memory, addr_arg(0)?, words_arg(1)?);
+                Ok(lines.iter().map(|line| format!("{}: {}", line.addr, line.text)).collect::<Vec<_>>().join("; "))
+            },
+            "quit" => Ok(String::new()),
+            "" => Err("empty request".to_string()),
+            _ => Err(format!("unknown command `{}`", command)),
+        }
+    }
+}
+
+/// Listens for debugger connections on a local TCP socket.
+pub struct DebugServer {
+    listener: TcpListener,
+}
+
+impl DebugServer {
+    /// Binds to `addr`. Port 0 picks a free port, which `local_addr` then reports.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<DebugServer> {
+        Ok(DebugServer { listener: TcpListener::bind(addr)? })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts one client and serves its requests against `prog` until it sends `quit` or disconnects.
+    pub fn serve(&self, prog: &mut Program) -> io::Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        serve_stream(stream, prog)
+    }
+}
+
+fn serve_stream(stream: TcpStream, prog: &mut Program) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut writer = BufWriter::new(stream.try_clone()?);
+    let mut session = DebugSession { prog, breakpoints: BTreeSet::new(), inputs: VecDeque::new() };
+    for line in BufReader::new(stream).lines() {
+        if !session.handle(&line?, &mut writer)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_debug_session() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpStream;
+
+        let server = DebugServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let server_thread = std::thread::spawn(move || {
+            // Doubles each input until it reads zero.
+            let mut prog = Program::from(&[3, 20, 1006, 20, 14, 1002, 20, 2, 21, 4, 21, 1105, 1, 0, 99][..]);
+            server.serve(&mut prog).unwrap();
+            prog
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request = |line: &str| -> Vec<String> {
+            writer.write_all(format!("{}\n", line).as_bytes()).unwrap();
+            let mut response = Vec::new();
+            loop {
+                let mut response_line = String::new();
+                reader.read_line(&mut response_line).unwrap();
+                let response_line = response_line.trim_end().to_string();
+                let done = response_line.starts_with("ok") || response_line.starts_with("err");
+                response.push(response_line);
+                if done {
+                    return response;
+                }
+            }
+        };
+
+        assert_eq!(request("regs"), vec!["ok pc=0 rb=0 halted=0"]);
+        assert_eq!(request("continue"), vec!["ok waiting pc=0"]);
+        assert_eq!(request("input 21 4"), vec!["ok"]);
+        assert_eq!(request("break 9"), vec!["ok"]);
+        assert_eq!(request("continue"), vec!["ok break pc=9"]);
+        assert_eq!(request("read 20 2"), vec!["ok 21 42"]);
+        assert_eq!(request("disasm 9 2"), vec!["ok 9: OUT [21]; 11: JNZ 1, 0"]);
+        assert_eq!(request("write 21 -7"), vec!["ok"]);
+        assert_eq!(request("step"), vec!["out -7", "ok running pc=11"]);
+        assert_eq!(request("continue"), vec!["ok break pc=9"]);
+        assert_eq!(request("delete 9"), vec!["ok"]);
+        assert_eq!(request("breakpoints"), vec!["ok"]);
+        assert_eq!(request("continue 100"), vec!["out 8", "ok waiting pc=0"]);
+        assert_eq!(request("bogus"), vec!["err unknown command `bogus`"]);
+        assert_eq!(request("read -1"), vec!["err invalid address -1"]);
+        assert_eq!(request("read 0 1000000000"), vec!["err count 1000000000 is over the limit of 1024"]);
+        assert_eq!(request("disasm 0 1000000000"), vec!["err count 1000000000 is over the limit of 1024"]);
+        assert_eq!(request("write 1152921504606846976 1"), vec!["err invalid address 1152921504606846976"]);
+        assert_eq!(request("write 0 42"), vec!["ok"]);
+        assert_eq!(request("step"), vec!["err PC=0 executed illegal instruction 42"]);
+        assert_eq!(request("continue"), vec!["err PC=0 executed illegal instruction 42"]);
+        assert_eq!(request("regs"), vec!["ok pc=0 rb=0 halted=1"]);
+        assert_eq!(request("quit"), vec!["ok"]);
+
+        let prog = server_thread.join().unwrap();
+        assert!(prog.is_halted());
+        assert!(prog.fault().is_some());
+        assert_eq!(prog.memory()[21], 8);
+    }
+}
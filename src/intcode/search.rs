@@ -0,0 +1,140 @@
+use super::Program;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+impl Program {
+    /// Forks this program for evaluating one candidate, so no per-run state such as a fault carries over from the previous
+    /// candidate. Journaling and crash dumps are off, so a faulting candidate doesn't write a dump file.
+    fn candidate_copy(&self) -> Program {
+        let mut copy = self.fork();
+        copy.journal = None;
+        copy.crash_recorder = None;
+        copy
+    }
+}
+
+fn worker_count(candidate_count: usize) -> usize {
+    std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(candidate_count)
+        .max(1)
+}
+
+/// Evaluates one candidate against a copy of `prog`: `setup` patches the copy and returns the inputs to feed it,
+/// then `predicate` inspects the finished program and its outputs.
+fn evaluate<C, S, P>(prog: &Program, candidate: &C, setup: &S, predicate: &P) -> bool
+where
+    S: Fn(&C, &mut Program) -> Vec<i64>,
+    P: Fn(&C, &Program, &[i64]) -> bool,
+{
+    let mut candidate_prog = prog.candidate_copy();
+    let inputs = setup(candidate, &mut candidate_prog);
+    let outputs = candidate_prog.run_with_inputs(&inputs);
+    predicate(candidate, &candidate_prog, &outputs)
+}
+
+/// Searches `candidates` on every CPU core and returns the index of the first candidate, in candidate order, that satisfies `predicate`.
+///
+/// Each candidate runs on a copy of `prog` in its current state. See `evaluate` for how `setup` and `predicate` are applied.
+pub fn find_first<C, S, P>(prog: &Program, candidates: &[C], setup: S, predicate: P) -> Option<usize>
+where
+    C: Sync,
+    S: Fn(&C, &mut Program) -> Vec<i64> + Sync,
+    P: Fn(&C, &Program, &[i64]) -> bool + Sync,
+{
+    let next_idx = AtomicUsize::new(0);
+    let best_idx = AtomicUsize::new(usize::MAX);
+    std::thread::scope(|scope| {
+        for _ in 0..worker_count(candidates.len()) {
+            scope.spawn(|| {
+                loop {
+                    let idx = next_idx.fetch_add(1, Ordering::Relaxed);
+                    // Indices are handed out in order, so once one is past the best match no later one can beat it.
+                    if idx >= candidates.len() || idx > best_idx.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if evaluate(prog, &candidates[idx], &setup, &predicate) {
+                        best_idx.fetch_min(idx, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    match best_idx.into_inner() {
+        usize::MAX => None,
+        idx => Some(idx),
+    }
+}
+
+/// Searches `candidates` on every CPU core and returns the indices of all candidates that satisfy `predicate`, in ascending order.
+pub fn find_all<C, S, P>(prog: &Program, candidates: &[C], setup: S, predicate: P) -> Vec<usize>
+where
+    C: Sync,
+    S: Fn(&C, &mut Program) -> Vec<i64> + Sync,
+    P: Fn(&C, &Program, &[i64]) -> bool + Sync,
+{
+    let next_idx = AtomicUsize::new(0);
+    let matches = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..worker_count(candidates.len()) {
+            scope.spawn(|| {
+                let mut worker_matches = Vec::new();
+                loop {
+                    let idx = next_idx.fetch_add(1, Ordering::Relaxed);
+                    if idx >= candidates.len() {
+                        break;
+                    }
+                    if evaluate(prog, &candidates[idx], &setup, &predicate) {
+                        worker_matches.push(idx);
+                    }
+                }
+                matches.lock().unwrap().extend(worker_matches);
+            });
+        }
+    });
+
+    let mut matches = matches.into_inner().unwrap();
+    matches.sort_unstable();
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn search_day2_noun_verb() {
+        let input = load_program("inputs/2019/day2.txt").unwrap();
+        let prog = Program::from(&input[..]);
+        let candidates: Vec<(i64, i64)> = (0..=99).flat_map(|noun| (0..=99).map(move |verb| (noun, verb))).collect();
+        let patch = |&(noun, verb): &(i64, i64), prog: &mut Program| {
+            prog.memory_mut().set(1, noun);
+            prog.memory_mut().set(2, verb);
+            Vec::new()
+        };
+
+        let found = find_first(&prog, &candidates, patch, |_, prog, _| prog.memory()[0] == 19690720);
+        assert_eq!(found.map(|idx| candidates[idx]), Some((41, 12)));
+        let found = find_all(&prog, &candidates, patch, |_, prog, _| prog.memory()[0] == 19690720);
+        assert_eq!(found.len(), 1);
+        assert_eq!(find_first(&prog, &candidates, patch, |_, _, _| false), None);
+        assert_eq!(find_all(&prog, &candidates, patch, |&(noun, _), _, _| noun == 0).len(), 100);
+    }
+
+    #[test]
+    fn search_after_faulting_candidate() {
+        // Odd inputs jump to an illegal opcode at 8; even ones output 1.
+        let mut prog = Program::from(&[3, 9, 1005, 9, 8, 104, 1, 99, 42, 0][..]);
+        prog.catch_faults();
+        let candidates: Vec<i64> = (0..64).map(|idx| idx % 2).collect();
+        let setup = |&input: &i64, _: &mut Program| vec![input];
+        let predicate = |_: &i64, prog: &Program, output: &[i64]| prog.fault().is_none() && output == [1];
+
+        assert_eq!(find_first(&prog, &[1, 1, 0], setup, predicate), Some(2));
+        let found = find_all(&prog, &candidates, setup, predicate);
+        assert_eq!(found, (0..64).step_by(2).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,143 @@
+use super::Program;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A write that landed on a cell previously executed as part of an instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CodeWrite {
+    /// PC of the instruction that performed the write.
+    pub pc: usize,
+    pub addr: usize,
+    pub old_value: i64,
+    pub new_value: i64,
+}
+
+impl fmt::Display for CodeWrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PC={} wrote {} over executed code at [{}] (was {})", self.pc, self.new_value, self.addr, self.old_value)
+    }
+}
+
+/// Tracks which cells have been executed as instructions (opcodes and their parameters) and records later writes to them.
+/// Executed cells are stored sparsely, since a program can write code anywhere and jump to it.
+#[derive(Debug, Clone, Default)]
+pub struct CodeTracker {
+    executed: BTreeSet<usize>,
+    code_writes: Vec<CodeWrite>,
+    warn: bool,
+}
+
+impl CodeTracker {
+    pub(super) fn mark_executed(&mut self, pc: usize, length: usize) {
+        self.executed.extend(pc..pc + length);
+    }
+
+    pub(super) fn check_write(&mut self, pc: usize, addr: usize, old_value: i64, new_value: i64) {
+        if !self.executed.contains(&addr) {
+            return;
+        }
+        let code_write = CodeWrite { pc, addr, old_value, new_value };
+        if self.warn {
+            eprintln!("Warning: self-modifying code: {}", code_write);
+        }
+        self.code_writes.push(code_write);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.executed.clear();
+        self.code_writes.clear();
+    }
+
+    #[allow(dead_code)]
+    pub fn was_executed(&self, addr: usize) -> bool {
+        self.executed.contains(&addr)
+    }
+
+    /// Every write to executed code so far, in execution order. This includes writes that stored the value already there.
+    #[allow(dead_code)]
+    pub fn code_writes(&self) -> &[CodeWrite] {
+        &self.code_writes
+    }
+
+    /// Whether any write actually changed the value of an executed cell.
+    #[allow(dead_code)]
+    pub fn modified_code(&self) -> bool {
+        self.code_writes.iter().any(|code_write| code_write.old_value != code_write.new_value)
+    }
+}
+
+impl Program {
+    /// Starts tracking executed cells so that writes to them are recorded. With `warn` set, each such write is also reported on stderr.
+    #[allow(dead_code)]
+    pub fn enable_code_tracking(&mut self, warn: bool) {
+        self.code_tracker = Some(CodeTracker { warn, ..CodeTracker::default() });
+    }
+
+    #[allow(dead_code)]
+    pub fn disable_code_tracking(&mut self) {
+        self.code_tracker = None;
+    }
+
+    #[allow(dead_code)]
+    pub fn code_tracker(&self) -> Option<&CodeTracker> {
+        self.code_tracker.as_ref()
+    }
+
+    /// Returns whether the program has modified its own code so far, or `None` if code tracking is disabled.
+    #[allow(dead_code)]
+    pub fn modified_own_code(&self) -> Option<bool> {
+        self.code_tracker.as_ref().map(CodeTracker::modified_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn selfmod_detects_writes_to_executed_code() {
+        // Overwrites its own first parameter after executing it, then writes to data that never ran.
+        let mut prog = Program::from(&[1101, 1, 1, 1, 1101, 2, 2, 9, 99, 0][..]);
+        prog.enable_code_tracking(false);
+        prog.run_with_inputs(&[]);
+        let code_tracker = prog.code_tracker().unwrap();
+        assert_eq!(code_tracker.code_writes(), &[CodeWrite { pc: 0, addr: 1, old_value: 1, new_value: 2 }]);
+        assert!(code_tracker.was_executed(8));
+        assert!(!code_tracker.was_executed(9));
+        assert_eq!(prog.modified_own_code(), Some(true));
+
+        // Patching code before it runs is not self-modification.
+        let mut prog = Program::from(&[1002, 4, 3, 4, 33][..]);
+        prog.enable_code_tracking(false);
+        prog.run_with_inputs(&[]);
+        assert_eq!(prog.modified_own_code(), Some(false));
+        prog.disable_code_tracking();
+        assert_eq!(prog.modified_own_code(), None);
+
+        // Code written high in memory is tracked without covering everything below it.
+        let mut prog = Program::from(&[1101, 0, 99, 100_000_000, 1105, 1, 100_000_000][..]);
+        prog.enable_code_tracking(false);
+        prog.run_with_inputs(&[]);
+        let code_tracker = prog.code_tracker().unwrap();
+        assert!(code_tracker.was_executed(100_000_000) && !code_tracker.was_executed(99_999_999));
+        assert_eq!(prog.modified_own_code(), Some(false));
+    }
+
+    #[test]
+    fn selfmod_puzzle_inputs() {
+        let input = load_program("inputs/2019/day9.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.enable_code_tracking(false);
+        assert_eq!(prog.run_with_inputs(&[1]), vec![3235019597]);
+        assert_eq!(prog.modified_own_code(), Some(false));
+
+        // Day 5's diagnostic program reuses its already executed first instruction as scratch space.
+        let input = load_program("inputs/2019/day5.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        prog.enable_code_tracking(false);
+        assert_eq!(prog.run_with_inputs(&[5]), vec![16694270]);
+        assert_eq!(prog.modified_own_code(), Some(true));
+        assert!(prog.code_tracker().unwrap().code_writes().iter().any(|code_write| code_write.addr == 0));
+    }
+}
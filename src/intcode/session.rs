@@ -0,0 +1,252 @@
+use super::{Program, IOOperation, IOReturn, ExecuteAction};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A single I/O event seen by a running program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    Input(i64),
+    Output(i64),
+}
+
+impl fmt::Display for SessionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionEvent::Input(value) => write!(f, "in {}", value),
+            SessionEvent::Output(value) => write!(f, "out {}", value),
+        }
+    }
+}
+
+/// An ordered record of every value a program consumed and produced.
+///
+/// Sessions are stored as plain text, one event per line (`in 5`, `out 3`). Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    events: Vec<SessionEvent>,
+}
+
+impl Session {
+    #[allow(dead_code)]
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn events(&self) -> &[SessionEvent] {
+        &self.events
+    }
+
+    /// Returns the recorded input values in the order they were consumed.
+    #[allow(dead_code)]
+    pub fn inputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            SessionEvent::Input(value) => Some(*value),
+            SessionEvent::Output(_) => None,
+        })
+    }
+
+    /// Returns the recorded output values in the order they were produced.
+    #[allow(dead_code)]
+    pub fn outputs(&self) -> impl Iterator<Item = i64> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            SessionEvent::Input(_) => None,
+            SessionEvent::Output(value) => Some(*value),
+        })
+    }
+
+    /// Wraps an I/O handler so that every input it supplies and every output it receives is appended to this session.
+    #[allow(dead_code)]
+    pub fn recorder<'a, F>(&'a mut self, mut io_handler: F) -> impl FnMut(IOOperation) -> IOReturn + 'a
+    where
+        F: FnMut(IOOperation) -> IOReturn + 'a
+    {
+        move |io_op| {
+            let io_return = io_handler(io_op);
+            match (io_op, io_return) {
+                (IOOperation::Input, IOReturn::Input(value)) => self.events.push(SessionEvent::Input(value)),
+                (IOOperation::Output(value), _) => self.events.push(SessionEvent::Output(value)),
+                // The handler paused instead of supplying a value, so nothing was consumed.
+                (IOOperation::Input, IOReturn::Output(_)) => (),
+            }
+            io_return
+        }
+    }
+
+    /// Runs `prog` to completion, feeding it the recorded inputs and checking every output against the recording.
+    ///
+    /// Returns the first point where the program's behaviour differs from the session.
+    #[allow(dead_code)]
+    pub fn replay(&self, prog: &mut Program) -> Result<(), Divergence> {
+        let mut event_idx = 0;
+        let mut divergence = None;
+        prog.run(|io_op| {
+            let expected = self.events.get(event_idx).copied();
+            match (io_op, expected) {
+                (IOOperation::Input, Some(SessionEvent::Input(value))) => {
+                    event_idx += 1;
+                    return IOReturn::Input(value);
+                },
+                (IOOperation::Output(actual), Some(SessionEvent::Output(expected))) if actual == expected => {
+                    event_idx += 1;
+                    return IOReturn::Output(ExecuteAction::Continue);
+                },
+                (IOOperation::Input, expected) => {
+                    divergence = Some(Divergence::UnexpectedInput { event: event_idx, expected });
+                },
+                (IOOperation::Output(actual), expected) => {
+                    divergence = Some(Divergence::UnexpectedOutput { event: event_idx, expected, actual });
+                },
+            }
+            IOReturn::Output(ExecuteAction::Break)
+        });
+
+        match divergence {
+            Some(divergence) => Err(divergence),
+            None if event_idx < self.events.len() => Err(Divergence::EarlyHalt { event: event_idx, expected: self.events[event_idx] }),
+            None => Ok(()),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Session, Box<dyn std::error::Error>> {
+        Ok(std::fs::read_to_string(path)?.parse()?)
+    }
+
+    #[allow(dead_code)]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl From<Vec<SessionEvent>> for Session {
+    fn from(events: Vec<SessionEvent>) -> Session {
+        Session { events }
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = SessionParseError;
+
+    fn from_str(s: &str) -> Result<Session, SessionParseError> {
+        let mut events = Vec::new();
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || SessionParseError { line: line_idx + 1, text: line.to_string() };
+            let mut parts = line.split_whitespace();
+            let kind = parts.next().ok_or_else(error)?;
+            let value = parts.next().and_then(|num_str| num_str.parse::<i64>().ok()).ok_or_else(error)?;
+            if parts.next().is_some() {
+                return Err(error());
+            }
+            events.push(match kind {
+                "in" => SessionEvent::Input(value),
+                "out" => SessionEvent::Output(value),
+                _ => return Err(error()),
+            });
+        }
+        Ok(Session { events })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionParseError {
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for SessionParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid session event on line {}: {:?}", self.line, self.text)
+    }
+}
+
+impl std::error::Error for SessionParseError {}
+
+/// Describes where a replayed program stopped matching its recorded session.
+///
+/// `event` is the index of the first session event that was not matched. `expected` is `None` when the program kept running past the end of the session.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    UnexpectedInput { event: usize, expected: Option<SessionEvent> },
+    UnexpectedOutput { event: usize, expected: Option<SessionEvent>, actual: i64 },
+    EarlyHalt { event: usize, expected: SessionEvent },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |expected: &Option<SessionEvent>| match expected {
+            Some(event) => format!("`{}`", event),
+            None => String::from("end of session"),
+        };
+        match self {
+            Divergence::UnexpectedInput { event, expected } =>
+                write!(f, "Event {}: program requested input, expected {}", event, describe(expected)),
+            Divergence::UnexpectedOutput { event, expected, actual } =>
+                write!(f, "Event {}: program output {}, expected {}", event, actual, describe(expected)),
+            Divergence::EarlyHalt { event, expected } =>
+                write!(f, "Event {}: program halted, expected `{}`", event, expected),
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::loader::load_program;
+
+    #[test]
+    fn session_record_and_replay() {
+        let input = load_program("inputs/2019/day5.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        let mut session = Session::new();
+        prog.run(session.recorder(|io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(5),
+                IOOperation::Output(_) => IOReturn::Output(ExecuteAction::Continue),
+            }
+        }));
+        assert_eq!(session.events(), &[SessionEvent::Input(5), SessionEvent::Output(16694270)]);
+        assert_eq!(session.inputs().collect::<Vec<i64>>(), vec![5]);
+
+        let path = std::env::temp_dir().join(format!("aoc2019-session-{}.txt", std::process::id()));
+        session.save(&path).unwrap();
+        let session = Session::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        prog.reset();
+        assert_eq!(session.replay(&mut prog), Ok(()));
+    }
+
+    #[test]
+    fn session_replay_divergence() {
+        let input = load_program("inputs/2019/day5.txt").unwrap();
+        let mut prog = Program::from(&input[..]);
+        let session = Session::from(vec![SessionEvent::Input(5), SessionEvent::Output(1)]);
+        assert_eq!(
+            session.replay(&mut prog),
+            Err(Divergence::UnexpectedOutput { event: 1, expected: Some(SessionEvent::Output(1)), actual: 16694270 })
+        );
+
+        prog.reset();
+        let session = Session::from(vec![SessionEvent::Output(16694270)]);
+        assert_eq!(
+            session.replay(&mut prog),
+            Err(Divergence::UnexpectedInput { event: 0, expected: Some(SessionEvent::Output(16694270)) })
+        );
+    }
+}
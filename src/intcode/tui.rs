@@ -0,0 +1,397 @@
+use super::prelude::*;
+use super::disasm;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+const FRAME_TIME: Duration = Duration::from_millis(33);
+const DISASM_COL: usize = 1;
+const DISASM_WIDTH: usize = 38;
+const MEMORY_COL: usize = 41;
+const MEMORY_COLS: usize = 4;
+const CELL_WIDTH: usize = 12;
+const PANE_ROWS: usize = 16;
+const IO_LOG_ROWS: usize = 6;
+/// How many already executed instructions are shown above the PC.
+const HISTORY_ROWS: usize = 4;
+/// How many steps a written cell stays highlighted for.
+const HIGHLIGHT_STEPS: u64 = 64;
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const REVERSE: &str = "\x1b[7m";
+const WRITTEN: &str = "\x1b[1;33m";
+const AT_PC: &str = "\x1b[1;36m";
+
+/// A keyboard command for a running `Visualiser`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Control {
+    Faster,
+    Slower,
+    TogglePause,
+    ToggleHex,
+    Quit,
+}
+
+impl Control {
+    pub fn from_char(ch: char) -> Option<Control> {
+        match ch {
+            '+' | '=' => Some(Control::Faster),
+            '-' | '_' => Some(Control::Slower),
+            'p' | ' ' => Some(Control::TogglePause),
+            'h' => Some(Control::ToggleHex),
+            'q' => Some(Control::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Reads controls from stdin on a background thread. The terminal stays in line mode, so commands are sent with Enter
+/// and several can be given on one line (e.g. `+++`).
+pub fn spawn_stdin_controls() -> Receiver<Control> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            for control in line.chars().filter_map(Control::from_char) {
+                if sender.send(control).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// Full-screen live view of a running Intcode machine, drawn with plain ANSI escapes.
+///
+/// Shows a disassembly window around the PC, a memory pane that highlights recently written cells, and the I/O log.
+pub struct Visualiser {
+    steps_per_second: f64,
+    paused: bool,
+    hex: bool,
+    step_count: u64,
+    waiting_for_input: bool,
+    io_log: VecDeque<String>,
+    /// (address, step it was written on), newest last.
+    recent_writes: VecDeque<(usize, u64)>,
+}
+
+impl Visualiser {
+    pub fn new(steps_per_second: f64) -> Visualiser {
+        Visualiser {
+            steps_per_second: steps_per_second.max(0.25),
+            paused: false,
+            hex: false,
+            step_count: 0,
+            waiting_for_input: false,
+            io_log: VecDeque::with_capacity(IO_LOG_ROWS),
+            recent_writes: VecDeque::new(),
+        }
+    }
+
+    fn apply(&mut self, control: Control) {
+        match control {
+            Control::Faster => self.steps_per_second *= 2.0,
+            Control::Slower => self.steps_per_second = (self.steps_per_second / 2.0).max(0.25),
+            Control::TogglePause => self.paused = !self.paused,
+            Control::ToggleHex => self.hex = !self.hex,
+            Control::Quit => (),
+        }
+    }
+
+    fn log_io(&mut self, line: String) {
+        if self.io_log.len() == IO_LOG_ROWS {
+            self.io_log.pop_front();
+        }
+        self.io_log.push_back(line);
+    }
+
+    /// Executes one instruction, recording its I/O and memory write for display.
+    fn step<F>(&mut self, prog: &mut Program, io_handler: &mut F)
+    where
+        F: FnMut(IOOperation) -> IOReturn
+    {
+        let pc = prog.pc();
+        let mut io_event = None;
+        let exec_action = prog.step(|io_op| {
+            let io_return = io_handler(io_op);
+            io_event = Some((io_op, io_return));
+            io_return
+        });
+
+        match io_event {
+            Some((IOOperation::Input, IOReturn::Input(value))) => self.log_io(format!("{:>6}: in  <- {}", pc, value)),
+            Some((IOOperation::Input, IOReturn::Output(_))) => {
+                // The input instruction did not execute, so there is no write to record.
+                self.waiting_for_input = true;
+                return;
+            },
+            Some((IOOperation::Output(value), _)) => {
+                let ascii = if (32..127).contains(&value) { format!("  '{}'", value as u8 as char) } else { String::new() };
+                self.log_io(format!("{:>6}: out -> {}{}", pc, value, ascii));
+            },
+            None => (),
+        }
+        if let ExecuteAction::Break = exec_action {
+            self.paused = true;
+        }
+
+        self.step_count += 1;
+        if let Some(addr) = prog.journal().and_then(|journal| journal.last_write()) {
+            self.recent_writes.retain(|&(written_addr, _)| written_addr != addr);
+            self.recent_writes.push_back((addr, self.step_count));
+        }
+        while let Some(&(_, step)) = self.recent_writes.front() {
+            if self.step_count - step < HIGHLIGHT_STEPS {
+                break;
+            }
+            self.recent_writes.pop_front();
+        }
+    }
+
+    /// Runs `prog` live, drawing to `out`, until it halts or stops for input and the user quits.
+    ///
+    /// The machine is journaled while it runs so the view can show recent history. If it wasn't journaled before, the
+    /// journal is turned off again afterwards.
+    pub fn run<F, W>(&mut self, prog: &mut Program, io_handler: F, controls: &Receiver<Control>, out: &mut W) -> io::Result<()>
+    where
+        F: FnMut(IOOperation) -> IOReturn,
+        W: Write,
+    {
+        let was_journaled = prog.journal().is_some();
+        if !was_journaled {
+            prog.enable_journal(HISTORY_ROWS + 1);
+        }
+        // Switch to the alternate screen and hide the cursor for the duration of the run.
+        let result = write!(out, "\x1b[?1049h\x1b[?25l")
+            .and_then(|_| self.run_loop(prog, io_handler, controls, out))
+            .and_then(|_| write!(out, "\x1b[?25h\x1b[?1049l"))
+            .and_then(|_| out.flush());
+        if !was_journaled {
+            prog.disable_journal();
+        }
+        result
+    }
+
+    fn run_loop<F, W>(&mut self, prog: &mut Program, mut io_handler: F, controls: &Receiver<Control>, out: &mut W) -> io::Result<()>
+    where
+        F: FnMut(IOOperation) -> IOReturn,
+        W: Write,
+    {
+        let mut step_budget = 0.0;
+        let mut last_tick = Instant::now();
+        let mut last_frame: Option<Instant> = None;
+        loop {
+            let stopped = prog.is_halted() || self.waiting_for_input;
+            loop {
+                let control = if stopped {
+                    // Nothing left to animate, so block until the user quits (or stdin closes).
+                    out.write_all(self.render(prog).as_bytes())?;
+                    out.flush()?;
+                    match controls.recv() {
+                        Ok(control) => control,
+                        Err(_) => return Ok(()),
+                    }
+                }
+                else {
+                    match controls.try_recv() {
+                        Ok(control) => control,
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => break,
+                    }
+                };
+                if let Control::Quit = control {
+                    return Ok(());
+                }
+                self.apply(control);
+            }
+
+            let now = Instant::now();
+            if !self.paused {
+                step_budget += now.duration_since(last_tick).as_secs_f64() * self.steps_per_second;
+                // Don't let a backlog build up if rendering falls behind.
+                step_budget = f64::min(step_budget, self.steps_per_second * FRAME_TIME.as_secs_f64() + 1.0);
+            }
+            last_tick = now;
+
+            while step_budget >= 1.0 && !self.paused && !prog.is_halted() && !self.waiting_for_input {
+                step_budget -= 1.0;
+                self.step(prog, &mut io_handler);
+            }
+
+            let frame_due = match last_frame {
+                Some(frame) => now.duration_since(frame) >= FRAME_TIME,
+                None => true,
+            };
+            if frame_due {
+                out.write_all(self.render(prog).as_bytes())?;
+                out.flush()?;
+                last_frame = Some(now);
+            }
+
+            let until_next_step = if self.paused { FRAME_TIME } else { Duration::from_secs_f64((1.0 - step_budget).max(0.0) / self.steps_per_second) };
+            std::thread::sleep(until_next_step.min(FRAME_TIME));
+        }
+    }
+
+    fn format_value(&self, value: i64) -> String {
+        if self.hex {
+            if value < 0 { format!("-{:x}", -(value as i128)) } else { format!("{:x}", value) }
+        }
+        else {
+            value.to_string()
+        }
+    }
+
+    fn write_age(&self, addr: usize) -> Option<u64> {
+        self.recent_writes
+            .iter()
+            .rev()
+            .find(|&&(written_addr, _)| written_addr == addr)
+            .map(|&(_, step)| self.step_count - step)
+    }
+
+    /// Draws a complete frame.
+    pub fn render(&self, prog: &Program) -> String {
+        let mut screen = String::from("\x1b[H\x1b[2J");
+        let state = if prog.is_halted() {
+            "halted"
+        }
+        else if self.waiting_for_input {
+            "waiting for input"
+        }
+        else if self.paused {
+            "paused"
+        }
+        else {
+            "running"
+        };
+        put(&mut screen, 1, 1, &format!(
+            "{}Intcode  PC={}  RB={}  steps={}  speed={}/s  [{}]{}",
+            REVERSE, prog.pc(), prog.relative_base(), self.step_count, self.steps_per_second, state, RESET,
+        ));
+
+        put(&mut screen, 3, DISASM_COL, "Disassembly");
+        let history: Vec<usize> = prog.journal()
+            .map(|journal| journal.pcs().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let history = &history[history.len().saturating_sub(HISTORY_ROWS)..];
+        let mut row = 4;
+        for &pc in history {
+            let line = disasm::disassemble_one(prog.memory(), pc);
+            put(&mut screen, row, DISASM_COL, &format!("{}  {:>6}  {}{}", DIM, line.addr, fit(&line.text, DISASM_WIDTH - 10), RESET));
+            row += 1;
+        }
+        for (line_idx, line) in disasm::disassemble(prog.memory(), prog.pc(), PANE_ROWS - history.len()).iter().enumerate() {
+            let text = fit(&line.text, DISASM_WIDTH - 10);
+            if line_idx == 0 {
+                put(&mut screen, row, DISASM_COL, &format!("{}> {:>6}  {:<width$}{}", REVERSE, line.addr, text, RESET, width = DISASM_WIDTH - 10));
+            }
+            else {
+                put(&mut screen, row, DISASM_COL, &format!("  {:>6}  {}", line.addr, text));
+            }
+            row += 1;
+        }
+
+        put(&mut screen, 3, MEMORY_COL, &format!("Memory ({})", if self.hex { "hex" } else { "decimal" }));
+        let focus = self.recent_writes.back().map_or(prog.pc(), |&(addr, _)| addr);
+        let first_row_addr = (focus / MEMORY_COLS).saturating_sub(PANE_ROWS / 2) * MEMORY_COLS;
+        for mem_row in 0..PANE_ROWS {
+            let row_addr = first_row_addr + mem_row * MEMORY_COLS;
+            let mut text = format!("{:>6}:", row_addr);
+            for addr in row_addr..row_addr + MEMORY_COLS {
+                let value = fit(&self.format_value(prog.memory().get(addr)), CELL_WIDTH - 1);
+                let style = if addr == prog.pc() {
+                    AT_PC
+                }
+                else {
+                    match self.write_age(addr) {
+                        Some(age) if age < HIGHLIGHT_STEPS => WRITTEN,
+                        _ => "",
+                    }
+                };
+                let _ = write!(text, " {}{:>width$}{}", style, value, if style.is_empty() { "" } else { RESET }, width = CELL_WIDTH - 1);
+            }
+            put(&mut screen, 4 + mem_row, MEMORY_COL, &text);
+        }
+
+        let io_row = 5 + PANE_ROWS;
+        put(&mut screen, io_row, 1, "I/O");
+        for (line_idx, line) in self.io_log.iter().enumerate() {
+            put(&mut screen, io_row + 1 + line_idx, 1, line);
+        }
+        put(&mut screen, io_row + 2 + IO_LOG_ROWS, 1, &format!(
+            "{}Controls (then Enter): + faster  - slower  p pause  h hex/decimal  q quit{}", DIM, RESET,
+        ));
+        screen
+    }
+}
+
+/// Moves the cursor to a 1-based row and column and writes `text` there.
+fn put(screen: &mut String, row: usize, col: usize, text: &str) {
+    let _ = write!(screen, "\x1b[{};{}H{}", row, col, text);
+}
+
+/// Truncates `text` to at most `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    }
+    else {
+        let mut fitted: String = text.chars().take(width.saturating_sub(1)).collect();
+        fitted.push('~');
+        fitted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tui_renders_machine_state() {
+        let mut prog = Program::from(&[3, 9, 1001, 9, 5, 9, 4, 9, 99, 0][..]);
+        let (sender, controls) = std::sync::mpsc::channel();
+        sender.send(Control::Quit).unwrap();
+        let mut visualiser = Visualiser::new(1000.0);
+        let mut screen = Vec::new();
+        let mut inputs = vec![37].into_iter();
+        visualiser.run(&mut prog, |io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(inputs.next().unwrap()),
+                IOOperation::Output(_) => IOReturn::Output(ExecuteAction::Continue),
+            }
+        }, &controls, &mut screen).unwrap();
+        assert!(!prog.is_halted());
+        assert!(prog.journal().is_none());
+
+        // A journal the caller enabled is left in place.
+        prog.enable_journal(16);
+        let mut visualiser = Visualiser::new(1.0e6);
+        let (sender, controls) = std::sync::mpsc::channel();
+        drop(sender);
+        visualiser.run(&mut prog, |io_op| {
+            match io_op {
+                IOOperation::Input => IOReturn::Input(37),
+                IOOperation::Output(_) => IOReturn::Output(ExecuteAction::Continue),
+            }
+        }, &controls, &mut screen).unwrap();
+        assert!(prog.is_halted());
+        assert!(prog.journal().is_some());
+
+        let frame = visualiser.render(&prog);
+        assert!(frame.contains("[halted]"));
+        assert!(frame.contains("ADD [9], 5, [9]"));
+        assert!(frame.contains("in  <- 37"));
+        assert!(frame.contains("out -> 42"));
+        // The written cell is highlighted.
+        assert!(frame.contains("\x1b[1;33m         42"));
+    }
+}
@@ -1,3 +1,5 @@
+#[macro_use]
+mod solution;
 mod intcode;
 mod day1;
 mod day2;
@@ -21,51 +23,299 @@ mod day19;
 // mod day20;
 mod day21;
 mod day22;
+mod answers;
+mod runner;
 
-const DAY_RUNNERS: [fn () -> Result<(), Box<dyn std::error::Error>>; 25] = [
-    day1::run,
-    day2::run,
-    day3::run,
-    day4::run,
-    day5::run,
-    day6::run,
-    day7::run,
-    day8::run,
-    day9::run,
-    day10::run,
-    day11::run,
-    day12::run,
-    day13::run,
-    day14::run,
-    empty_run,
-    day16::run,
-    empty_run,
-    empty_run,
-    day19::run,
-    empty_run,
-    day21::run,
-    day22::run,
-    empty_run,
-    empty_run,
-    empty_run,
-];
-
-fn empty_run() -> Result<(), Box<dyn std::error::Error>> { unimplemented!() }
+use std::path::Path;
 
 fn print_usage_and_exit() -> ! {
     let exe_path = std::env::current_exe().unwrap();
     let exe_name = exe_path.file_name().unwrap().to_str().unwrap();
-    println!("Delta's AoC 2019 solutions\nUsage: {} day", exe_name);
+    println!("Delta's AoC 2019 solutions\nUsage: {} [--input-dir <dir>] [--crash-dumps <dir>] day [--input <path> | --input -]", exe_name);
+    println!("       {} [--input-dir <dir>] [--crash-dumps <dir>] [--check] all | <day> | <first-day>-<last-day>", exe_name);
+    println!("       {} [--input-dir <dir>] inputs check", exe_name);
+    println!("       {} [--crash-dumps <dir>] memdiff <program> [--patch addr=value]... [inputs] [other-inputs]", exe_name);
+    println!("       {} [--crash-dumps <dir>] visualise <program> [inputs] [steps-per-second]", exe_name);
+    println!("       {} [--crash-dumps <dir>] debug <program> [port]", exe_name);
+    println!("       {} [--crash-dumps <dir>] coverage <program> [inputs | @ascii-file]...", exe_name);
+    println!("       {} postmortem <dump> [context-lines]", exe_name);
+    println!("       {} progdiff <program> <other-program>", exe_name);
     std::process::exit(0)
 }
 
+/// Creates a program for one of the Intcode tools, writing a crash dump into `crash_dir` if it faults. A fault stops
+/// the program without panicking, so the tool can still show what the program did; see `report_fault`.
+fn tool_program(image: &[i64], crash_dir: Option<&Path>) -> intcode::Program {
+    let mut prog = intcode::Program::from(image);
+    prog.catch_faults();
+    if let Some(crash_dir) = crash_dir {
+        prog.enable_crash_dumps(crash_dir, intcode::crashdump::DEFAULT_HISTORY);
+    }
+    prog
+}
+
+fn report_fault(prog: &intcode::Program) {
+    if let Some(fault) = prog.fault() {
+        eprintln!("The program faulted: {}", fault);
+    }
+}
+
+fn parse_value_list(list: &str) -> Result<Vec<i64>, std::num::ParseIntError> {
+    list
+        .split(',')
+        .filter(|num_str| !num_str.trim().is_empty())
+        .map(|num_str| num_str.trim().parse::<i64>())
+        .collect()
+}
+
+/// Runs an Intcode program and prints every memory cell that differs from its original image.
+/// Given a second comma-separated input list, prints the cells that differ between the two runs instead.
+fn run_memdiff(args: impl Iterator<Item = String>, crash_dir: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = args.peekable();
+    let path = match args.next() {
+        Some(path) => path,
+        None => print_usage_and_exit(),
+    };
+    let mut image = intcode::loader::load_program(&path)?;
+    while args.peek().map(String::as_str) == Some("--patch") {
+        args.next();
+        let patch = args.next().ok_or("--patch needs an addr=value argument")?;
+        let mut parts = patch.splitn(2, '=');
+        let addr = parts.next().unwrap_or("").parse::<usize>()?;
+        let value = parts.next().ok_or("--patch needs an addr=value argument")?.parse::<i64>()?;
+        if addr >= image.len() {
+            image.resize(addr + 1, 0);
+        }
+        image[addr] = value;
+    }
+    let inputs = parse_value_list(&args.next().unwrap_or_default())?;
+
+    let diffs = match args.next() {
+        Some(other_inputs) => intcode::memdiff::diff_runs(&image, &inputs, &parse_value_list(&other_inputs)?),
+        None => {
+            let mut prog = tool_program(&image, crash_dir);
+            let outputs = prog.run_with_inputs(&inputs);
+            report_fault(&prog);
+            println!("Outputs: {:?}", outputs);
+            prog.diff_from_default()
+        },
+    };
+    for diff in &diffs {
+        println!("{}", diff);
+    }
+    println!("{} cells differ", diffs.len());
+    Ok(())
+}
+
+/// Runs an Intcode program in the full-screen visualiser, feeding it a comma-separated input list.
+fn run_visualise(mut args: impl Iterator<Item = String>, crash_dir: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match args.next() {
+        Some(path) => path,
+        None => print_usage_and_exit(),
+    };
+    let image = intcode::loader::load_program(&path)?;
+    let mut inputs = parse_value_list(&args.next().unwrap_or_default())?.into_iter();
+    let steps_per_second = match args.next() {
+        Some(speed_str) => speed_str.parse::<f64>()?,
+        None => 20.0,
+    };
+
+    let mut prog = tool_program(&image, crash_dir);
+    let mut visualiser = intcode::tui::Visualiser::new(steps_per_second);
+    let controls = intcode::tui::spawn_stdin_controls();
+    visualiser.run(&mut prog, |io_op| {
+        match io_op {
+            intcode::IOOperation::Input => match inputs.next() {
+                Some(value) => intcode::IOReturn::Input(value),
+                None => intcode::IOReturn::Output(intcode::ExecuteAction::Break),
+            },
+            intcode::IOOperation::Output(_) => intcode::IOReturn::Output(intcode::ExecuteAction::Continue),
+        }
+    }, &controls, &mut std::io::stdout())?;
+    Ok(())
+}
+
+/// Runs an Intcode program once per input list and prints a disassembly annotated with the combined coverage.
+/// An input list starting with `@` names a text file whose bytes are fed in as ASCII, as for springscript.
+fn run_coverage(mut args: impl Iterator<Item = String>, crash_dir: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match args.next() {
+        Some(path) => path,
+        None => print_usage_and_exit(),
+    };
+    let image = intcode::loader::load_program(&path)?;
+    let mut input_lists = Vec::new();
+    for arg in args {
+        input_lists.push(match arg.strip_prefix('@') {
+            Some(ascii_path) => std::fs::read(ascii_path)?.into_iter().map(i64::from).collect(),
+            None => parse_value_list(&arg)?,
+        });
+    }
+    if input_lists.is_empty() {
+        input_lists.push(Vec::new());
+    }
+
+    let mut prog = tool_program(&image, crash_dir);
+    prog.enable_coverage();
+    for inputs in &input_lists {
+        prog.reset();
+        prog.run_with_inputs(inputs);
+        report_fault(&prog);
+    }
+    let coverage = prog.coverage().unwrap();
+    print!("{}", coverage.annotate(prog.memory(), image.len()));
+    println!("{}", coverage.summary(prog.memory(), image.len()));
+    Ok(())
+}
+
+/// Prints the post-mortem report for an Intcode crash dump.
+fn run_postmortem(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match args.next() {
+        Some(path) => path,
+        None => print_usage_and_exit(),
+    };
+    let context = match args.next() {
+        Some(context_str) => context_str.parse::<usize>()?,
+        None => 10,
+    };
+    print!("{}", intcode::crashdump::CrashDump::load(&path)?.report(context));
+    Ok(())
+}
+
+/// Compares two Intcode programs instruction by instruction, e.g. the same day's input from two accounts.
+fn run_progdiff(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let (left_path, right_path) = match (args.next(), args.next()) {
+        (Some(left_path), Some(right_path)) => (left_path, right_path),
+        _ => print_usage_and_exit(),
+    };
+    let left = intcode::loader::load_program(&left_path)?;
+    let right = intcode::loader::load_program(&right_path)?;
+
+    let diff = intcode::progdiff::diff_programs(&left, &right);
+    let summary = diff.summary();
+    print!("{}", diff);
+    println!("{}", summary);
+    if summary.same_structure() {
+        println!("Same code structure; the programs differ only in constants and data");
+    }
+    Ok(())
+}
+
+/// Runs every implemented day in `days` and prints a summary table, failing if any part panicked. With `check`, answers
+/// are also compared with the input directory's answers file, failing if any are wrong.
+fn run_days(days: std::ops::RangeInclusive<usize>, input_dir: &Path, check: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let answers = if check {
+        let path = input_dir.join(answers::ANSWERS_FILE);
+        Some(answers::Answers::load(&path).map_err(|err| format!("Couldn't load answers from {}: {}", path.display(), err))?)
+    }
+    else {
+        None
+    };
+
+    let outcomes = runner::run_days(days, input_dir, answers.as_ref());
+    println!();
+    print!("{}", runner::format_summary(&outcomes));
+    let panicked = outcomes.iter().filter(|outcome| matches!(outcome.status, runner::Status::Panicked(_))).count();
+    let wrong = outcomes.iter().filter(|outcome| matches!(outcome.verdict, Some(answers::Verdict::Wrong { .. }))).count();
+    if panicked > 0 || wrong > 0 {
+        return Err(format!("{} parts panicked, {} answers wrong", panicked, wrong).into());
+    }
+    Ok(())
+}
+
+/// Lists which days have a local input, failing if any implemented day is missing one.
+fn run_inputs(mut args: impl Iterator<Item = String>, input_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    match args.next() {
+        Some(ref cmd) if cmd == "check" => (),
+        _ => print_usage_and_exit(),
+    }
+    println!("Input directory: {}", input_dir.display());
+    let mut missing = 0;
+    for check in runner::check_inputs(input_dir) {
+        let status = match (check.size, check.implemented) {
+            (Some(size), _) => format!("{} ({} bytes)", check.path.display(), size),
+            (None, true) => {
+                missing += 1;
+                format!("MISSING, expected {}", check.path.display())
+            },
+            (None, false) => "-".to_string(),
+        };
+        println!("{:>3}  {:<15}  {}", check.day, if check.implemented { "implemented" } else { "not implemented" }, status);
+    }
+    if missing > 0 {
+        return Err(format!("{} implemented days have no input", missing).into());
+    }
+    Ok(())
+}
+
+/// Serves an Intcode program to one remote debugger client on localhost.
+fn run_debug(mut args: impl Iterator<Item = String>, crash_dir: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match args.next() {
+        Some(path) => path,
+        None => print_usage_and_exit(),
+    };
+    let image = intcode::loader::load_program(&path)?;
+    let port = match args.next() {
+        Some(port_str) => port_str.parse::<u16>()?,
+        None => intcode::remote::DEFAULT_PORT,
+    };
+
+    let server = intcode::remote::DebugServer::bind(("127.0.0.1", port))?;
+    println!("Waiting for a debugger on {}", server.local_addr()?);
+    let mut prog = tool_program(&image, crash_dir);
+    server.serve(&mut prog)?;
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut args = std::env::args();
-    args.next(); // ignore executable path
+    let mut args: Vec<String> = std::env::args().skip(1).collect(); // skip executable path
+    let input_dir_flag = match args.iter().position(|arg| arg == "--input-dir") {
+        Some(flag_idx) if flag_idx + 1 < args.len() => Some(args.drain(flag_idx..flag_idx + 2).nth(1).unwrap()),
+        Some(_) => return Err("--input-dir needs a directory".into()),
+        None => None,
+    };
+    let input_dir = runner::input_dir(input_dir_flag);
+    let crash_dir = match args.iter().position(|arg| arg == "--crash-dumps") {
+        Some(flag_idx) if flag_idx + 1 < args.len() => Some(std::path::PathBuf::from(args.drain(flag_idx..flag_idx + 2).nth(1).unwrap())),
+        Some(_) => return Err("--crash-dumps needs a directory".into()),
+        None => None,
+    };
+    let crash_dir = crash_dir.as_deref();
+    let check = match args.iter().position(|arg| arg == "--check") {
+        Some(flag_idx) => {
+            args.remove(flag_idx);
+            true
+        },
+        None => false,
+    };
+    let mut args = args.into_iter();
     let day = match args.next() {
-        Some(day_str) => day_str.parse::<usize>()?,
+        Some(ref cmd) if cmd == "memdiff" => return run_memdiff(args, crash_dir),
+        Some(ref cmd) if cmd == "visualise" => return run_visualise(args, crash_dir),
+        Some(ref cmd) if cmd == "debug" => return run_debug(args, crash_dir),
+        Some(ref cmd) if cmd == "coverage" => return run_coverage(args, crash_dir),
+        Some(ref cmd) if cmd == "postmortem" => return run_postmortem(args),
+        Some(ref cmd) if cmd == "progdiff" => return run_progdiff(args),
+        Some(ref cmd) if cmd == "inputs" => return run_inputs(args, &input_dir),
+        Some(ref days) if days == "all" || days.contains('-') => {
+            return intcode::crashdump::with_crash_dumps(crash_dir, || run_days(runner::parse_days(days)?, &input_dir, check))
+        },
+        Some(ref day_str) if check => {
+            return intcode::crashdump::with_crash_dumps(crash_dir, || run_days(runner::parse_days(day_str)?, &input_dir, true))
+        },
+        Some(day_str) => runner::parse_days(&day_str)?.start().to_owned(),
         None => print_usage_and_exit(),
     };
-    DAY_RUNNERS[day - 1]()?;
+    let input_source = match args.next() {
+        Some(ref flag) if flag == "--input" => Some(args.next().ok_or("--input needs a path, or - for stdin")?),
+        Some(arg) => return Err(format!("unexpected argument `{}`", arg).into()),
+        None => None,
+    };
+
+    let entry = solution::find(day).ok_or("This day hasn't been implemented yet")?;
+    let input = match input_source {
+        Some(source) => runner::read_input(&source).map_err(|err| format!("Couldn't read input {}: {}", source, err))?,
+        None => runner::load_input(&input_dir, day).map_err(|err| err.to_string())?,
+    };
+    intcode::crashdump::with_crash_dumps(crash_dir, || runner::run_with_input(entry, &input));
     Ok(())
 }
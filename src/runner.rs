@@ -0,0 +1,263 @@
+//! Runs a range of days in one go and summarises the answers, reading inputs from a local directory.
+//!
+//! Inputs are never downloaded: a missing input is reported with the path it was expected at.
+
+use crate::answers::{Answers, Verdict};
+use crate::solution::{self, DayEntry};
+use std::fmt;
+use std::io::Read;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Panicked(String),
+    NoInput(String),
+    NotImplemented,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartOutcome {
+    pub day: usize,
+    pub part: usize,
+    pub answer: Option<String>,
+    pub status: Status,
+    /// How the answer compares with the expected one, when checking answers.
+    pub verdict: Option<Verdict>,
+    /// Time spent parsing the input and solving the part.
+    pub elapsed: Duration,
+}
+
+/// Parses `all`, a single day or an inclusive range such as `1-13`.
+pub fn parse_days(arg: &str) -> Result<RangeInclusive<usize>, String> {
+    let parse_day = |day_str: &str| match day_str.trim().parse::<usize>() {
+        Ok(day) if (1..=25).contains(&day) => Ok(day),
+        _ => Err(format!("invalid day `{}` (expected 1 to 25)", day_str)),
+    };
+    if arg == "all" {
+        return Ok(1..=25);
+    }
+    match arg.find('-') {
+        Some(dash_idx) => {
+            let (first, last) = (parse_day(&arg[..dash_idx])?, parse_day(&arg[dash_idx + 1..])?);
+            if first > last {
+                return Err(format!("day range `{}` is backwards", arg));
+            }
+            Ok(first..=last)
+        },
+        None => parse_day(arg).map(|day| day..=day),
+    }
+}
+
+pub const DEFAULT_INPUT_DIR: &str = "inputs/2019";
+/// Environment variable overriding the input directory when `--input-dir` isn't given.
+pub const INPUT_DIR_VAR: &str = "AOC_INPUT_DIR";
+
+/// Picks the input directory: `--input-dir` if given, then `AOC_INPUT_DIR`, then `inputs/2019`.
+pub fn input_dir(flag: Option<String>) -> PathBuf {
+    flag.or_else(|| std::env::var(INPUT_DIR_VAR).ok())
+        .unwrap_or_else(|| DEFAULT_INPUT_DIR.to_string())
+        .into()
+}
+
+pub fn input_path(dir: &Path, day: usize) -> PathBuf {
+    dir.join(format!("day{}.txt", day))
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Missing { day: usize, path: PathBuf },
+    Unreadable { path: PathBuf, err: std::io::Error },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Missing { day, path } => write!(
+                f,
+                "No input for day {}: expected {} (inputs are never downloaded; save it there, or use --input-dir or --input)",
+                day,
+                path.display(),
+            ),
+            InputError::Unreadable { path, err } => write!(f, "Couldn't read input {}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Reads a day's input from `dir`.
+pub fn load_input(dir: &Path, day: usize) -> Result<String, InputError> {
+    let path = input_path(dir, day);
+    std::fs::read_to_string(&path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => InputError::Missing { day, path },
+        _ => InputError::Unreadable { path, err },
+    })
+}
+
+/// Reads an input given with `--input`: a file path, or `-` for stdin.
+pub fn read_input(source: &str) -> std::io::Result<String> {
+    if source == "-" {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    }
+    else {
+        std::fs::read_to_string(source)
+    }
+}
+
+fn print_answer(day: usize, part: usize, answer: &str) {
+    if answer.contains('\n') {
+        println!("Day {} part {}:\n{}", day, part, answer);
+    }
+    else {
+        println!("Day {} part {}: {}", day, part, answer);
+    }
+}
+
+/// Solves every implemented part of a day on the given input, printing the answers.
+pub fn run_with_input(entry: &DayEntry, input: &str) {
+    for part in 1..=2 {
+        if let Some(answer) = (entry.solve)(part, input.to_string()) {
+            print_answer(entry.day, part, &answer);
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(|| "unknown panic".to_string(), |message| message.to_string()),
+    }
+}
+
+fn run_part(entry: &DayEntry, part: usize, input: &str) -> PartOutcome {
+    let (day, input) = (entry.day, input.to_string());
+    let start = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| (entry.solve)(part, input)));
+    let elapsed = start.elapsed();
+    match result {
+        Ok(Some(answer)) => PartOutcome { day, part, answer: Some(answer), status: Status::Ok, verdict: None, elapsed },
+        Ok(None) => PartOutcome { day, part, answer: None, status: Status::NotImplemented, verdict: None, elapsed: Duration::default() },
+        Err(payload) => {
+            PartOutcome { day, part, answer: None, status: Status::Panicked(panic_message(payload)), verdict: None, elapsed }
+        },
+    }
+}
+
+/// Runs every day in `days` on its input in `input_dir`, printing each answer as it's found and checking it against
+/// `answers` if given. A day that isn't implemented, or whose input can't be read, is recorded without being run, and a
+/// panicking part doesn't stop the days after it.
+pub fn run_days(days: RangeInclusive<usize>, input_dir: &Path, answers: Option<&Answers>) -> Vec<PartOutcome> {
+    let mut outcomes = Vec::new();
+    for day in days {
+        let skipped = |part, status| PartOutcome { day, part, answer: None, status, verdict: None, elapsed: Duration::default() };
+        let entry = match solution::find(day) {
+            Some(entry) => entry,
+            None => {
+                outcomes.push(skipped(1, Status::NotImplemented));
+                outcomes.push(skipped(2, Status::NotImplemented));
+                continue;
+            },
+        };
+        let input = match load_input(input_dir, day) {
+            Ok(input) => input,
+            Err(err) => {
+                let status = Status::NoInput(match err {
+                    InputError::Missing { path, .. } => format!("expected {}", path.display()),
+                    err => err.to_string(),
+                });
+                outcomes.push(skipped(1, status.clone()));
+                outcomes.push(skipped(2, status));
+                continue;
+            },
+        };
+
+        for part in 1..=2 {
+            let mut outcome = run_part(entry, part, &input);
+            if let (Some(answers), Some(answer)) = (answers, &outcome.answer) {
+                outcome.verdict = Some(answers.check(day, part, answer));
+            }
+            if let Some(answer) = &outcome.answer {
+                print_answer(day, part, answer);
+            }
+            outcomes.push(outcome);
+        }
+    }
+    outcomes
+}
+
+/// Formats the outcomes as a table. Multi-line answers were printed in full as they ran, so only their size is shown.
+pub fn format_summary(outcomes: &[PartOutcome]) -> String {
+    let rows: Vec<[String; 5]> = outcomes
+        .iter()
+        .map(|outcome| {
+            let answer = match &outcome.answer {
+                Some(answer) if answer.contains('\n') => format!("({} lines, above)", answer.lines().count()),
+                Some(answer) => answer.clone(),
+                None => "-".to_string(),
+            };
+            let (status, elapsed) = match &outcome.status {
+                Status::Ok => {
+                    let status = match &outcome.verdict {
+                        None => "ok".to_string(),
+                        Some(Verdict::Correct) => "correct".to_string(),
+                        Some(Verdict::Wrong { expected }) => format!("WRONG (expected {})", expected),
+                        Some(Verdict::Unchecked) => "unchecked".to_string(),
+                    };
+                    (status, format!("{:.2?}", outcome.elapsed))
+                },
+                Status::Panicked(message) => (format!("panicked: {}", message), format!("{:.2?}", outcome.elapsed)),
+                Status::NoInput(reason) => (format!("no input ({})", reason), "-".to_string()),
+                Status::NotImplemented => ("not implemented".to_string(), "-".to_string()),
+            };
+            [outcome.day.to_string(), outcome.part.to_string(), answer, status, elapsed]
+        })
+        .collect();
+
+    let header = ["Day", "Part", "Answer", "Status", "Time"];
+    let mut widths: Vec<usize> = header.iter().map(|title| title.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let format_row = |cells: &[String]| {
+        let line = format!(
+            "{:>w0$}  {:>w1$}  {:<w2$}  {:<w3$}  {:>w4$}",
+            cells[0], cells[1], cells[2], cells[3], cells[4],
+            w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3], w4 = widths[4],
+        );
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut table = format_row(&header.iter().map(|title| title.to_string()).collect::<Vec<_>>());
+    for row in &rows {
+        table.push_str(&format_row(row));
+    }
+    table
+}
+
+/// Whether a day is implemented and has a local input.
+#[derive(Debug, Clone)]
+pub struct InputCheck {
+    pub day: usize,
+    pub implemented: bool,
+    pub path: PathBuf,
+    /// The input's size in bytes, if it exists.
+    pub size: Option<u64>,
+}
+
+pub fn check_inputs(input_dir: &Path) -> Vec<InputCheck> {
+    (1..=25)
+        .map(|day| {
+            let path = input_path(input_dir, day);
+            let size = std::fs::metadata(&path).ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.len());
+            InputCheck { day, implemented: solution::find(day).is_some(), path, size }
+        })
+        .collect()
+}
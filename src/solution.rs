@@ -0,0 +1,114 @@
+//! The `Solution` trait every day implements, and the registry of implemented days.
+
+use crate::*;
+use std::fmt::Display;
+
+pub trait Solution {
+    const DAY: usize;
+    type Input;
+    type Answer1: Display;
+    type Answer2: Display;
+
+    fn parse(input: String) -> Self::Input;
+    fn part1(input: Self::Input) -> Self::Answer1;
+
+    /// Parses the raw input and solves part 2, or returns `None` if it isn't implemented. It takes the raw input so that
+    /// an unimplemented part 2 is skipped without parsing anything.
+    fn part2(_input: String) -> Option<Self::Answer2> {
+        None
+    }
+}
+
+/// Implements `Solution` for a day by forwarding to the day module's input generator and `part1`/`part2` functions.
+/// Leave out part 2's answer type if it isn't implemented.
+///
+/// `solution!(Day2: 2, Vec<i64> = intcode_gen, i64, i64);`
+macro_rules! solution {
+    ($name:ident: $day:expr, $input:ty = $gen:path, $answer1:ty) => {
+        pub(crate) struct $name;
+
+        impl crate::solution::Solution for $name {
+            const DAY: usize = $day;
+            type Input = $input;
+            type Answer1 = $answer1;
+            type Answer2 = std::convert::Infallible;
+
+            fn parse(input: String) -> $input {
+                $gen(input)
+            }
+
+            fn part1(input: $input) -> $answer1 {
+                part1(input)
+            }
+        }
+    };
+    ($name:ident: $day:expr, $input:ty = $gen:path, $answer1:ty, $answer2:ty) => {
+        pub(crate) struct $name;
+
+        impl crate::solution::Solution for $name {
+            const DAY: usize = $day;
+            type Input = $input;
+            type Answer1 = $answer1;
+            type Answer2 = $answer2;
+
+            fn parse(input: String) -> $input {
+                $gen(input)
+            }
+
+            fn part1(input: $input) -> $answer1 {
+                part1(input)
+            }
+
+            fn part2(input: String) -> Option<$answer2> {
+                Some(part2($gen(input)))
+            }
+        }
+    };
+}
+
+/// A type-erased `Solution`.
+pub struct DayEntry {
+    pub day: usize,
+    /// Parses the raw input and solves one part, formatting the answer, or returns `None` if the part isn't implemented.
+    pub solve: fn(usize, String) -> Option<String>,
+}
+
+impl DayEntry {
+    pub const fn of<S: Solution>() -> DayEntry {
+        DayEntry { day: S::DAY, solve: solve_part::<S> }
+    }
+}
+
+fn solve_part<S: Solution>(part: usize, input: String) -> Option<String> {
+    match part {
+        1 => Some(S::part1(S::parse(input)).to_string()),
+        2 => S::part2(input).map(|answer| answer.to_string()),
+        _ => panic!("Day {} has no part {}", S::DAY, part),
+    }
+}
+
+/// Every implemented day, in order.
+pub const DAYS: &[DayEntry] = &[
+    DayEntry::of::<day1::Day1>(),
+    DayEntry::of::<day2::Day2>(),
+    DayEntry::of::<day3::Day3>(),
+    DayEntry::of::<day4::Day4>(),
+    DayEntry::of::<day5::Day5>(),
+    DayEntry::of::<day6::Day6>(),
+    DayEntry::of::<day7::Day7>(),
+    DayEntry::of::<day8::Day8>(),
+    DayEntry::of::<day9::Day9>(),
+    DayEntry::of::<day10::Day10>(),
+    DayEntry::of::<day11::Day11>(),
+    DayEntry::of::<day12::Day12>(),
+    DayEntry::of::<day13::Day13>(),
+    DayEntry::of::<day14::Day14>(),
+    DayEntry::of::<day16::Day16>(),
+    DayEntry::of::<day19::Day19>(),
+    DayEntry::of::<day21::Day21>(),
+    DayEntry::of::<day22::Day22>(),
+];
+
+pub fn find(day: usize) -> Option<&'static DayEntry> {
+    DAYS.iter().find(|entry| entry.day == day)
+}